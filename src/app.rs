@@ -0,0 +1,3636 @@
+//! Application state, update logic and views.
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use iced::theme::Button as ButtonTheme;
+use iced::widget::{
+    button, checkbox, column, container, horizontal_space, mouse_area, pick_list, row, scrollable,
+    slider, text, text_editor, text_input, tooltip, vertical_rule,
+};
+use iced::{event, keyboard, mouse, window};
+use iced::{time, Application, Color, Command, Element, Font, Length, Subscription, Theme};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::menu::{self, Menu};
+use crate::message::Message;
+use crate::note::{
+    analyze, normalize_title, note_stats, snippet, truncate_display, Note, NoteColor, DEFAULT_TITLE,
+};
+use crate::palette::{self, Palette};
+use crate::replace::{self, FindBar};
+use crate::storage::{self, Preferences, WindowGeometry, NOTES_FILE};
+use crate::style::{AccentStripe, DropZone, NeutralNoteButtonStyle, NoteButtonStyle, SidebarStyle};
+use crate::toast::{self, Severity, Toast, TOAST_DURATION};
+use crate::{checklist, crypto, links, markdown, search, trash, whats_new};
+
+mod transfer;
+
+/// How often "edited … ago" labels are refreshed.
+const CLOCK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Pending edits are written to `NotesApp::storage_path` once typing has
+/// paused for the autosave interval, so a burst of keystrokes becomes a
+/// single write. An interval of 0 turns autosave off.
+pub const DEFAULT_AUTOSAVE_INTERVAL_SECS: u64 = 2;
+pub const MAX_AUTOSAVE_INTERVAL_SECS: u64 = 300;
+
+/// Changed preferences are written once editing has paused for this long.
+const PREFERENCES_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Notes longer than this, in bytes, only re-render their Markdown preview
+/// when asked to, since laying out a huge document on every keystroke
+/// makes typing stutter.
+const PREVIEW_LIVE_LIMIT: usize = 100_000;
+
+/// Longest first line shown in the editor's header, in characters.
+const HEADER_LINE_LEN: usize = 80;
+
+/// How long the cursor rests on a pinned note before its content peeks out,
+/// how many lines of it are shown, and how many characters of each.
+const PEEK_DELAY: Duration = Duration::from_millis(500);
+const PEEK_LINES: usize = 6;
+const PEEK_LINE_LEN: usize = 60;
+
+/// How often to check whether the debounce period has passed.
+const SAVE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The default lowest word overlap for "Find Similar" to list a note.
+pub const DEFAULT_SIMILARITY_THRESHOLD: f32 = 0.3;
+/// How many similar notes the sidebar lists at most.
+const SIMILAR_LIMIT: usize = 10;
+
+/// Share of the width between the sidebar and the editor given to the
+/// sidebar, by default and at most/least when dragging the divider.
+pub const DEFAULT_SPLIT_RATIO: f32 = 0.25;
+const MIN_SPLIT_RATIO: f32 = 0.15;
+const MAX_SPLIT_RATIO: f32 = 0.6;
+
+/// Horizontal layout metrics, needed to turn a cursor position over the
+/// divider back into a split ratio.
+const WINDOW_PADDING: f32 = 20.0;
+const PANE_SPACING: f32 = 20.0;
+const NOTEBOOK_RAIL_WIDTH: f32 = 150.0;
+const DIVIDER_WIDTH: f32 = 8.0;
+
+/// The widest the editor grows in focus mode, and its margin from the
+/// window's edges, so lines stay a comfortable length to read.
+const ZEN_MAX_WIDTH: f32 = 760.0;
+const ZEN_PADDING: f32 = 40.0;
+
+/// Notes due within this window show up under the "Due soon" filter.
+const DUE_SOON: chrono::Duration = chrono::Duration::hours(24);
+
+/// Two clicks on the same sidebar note within this interval rename it.
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Edits to the same note closer together than this share one undo entry.
+const UNDO_COALESCE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Editor zoom limits and the size "Reset Zoom" returns to.
+pub const DEFAULT_FONT_SIZE: u16 = 16;
+pub const MIN_FONT_SIZE: u16 = 10;
+pub const MAX_FONT_SIZE: u16 = 40;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortMode {
+    #[default]
+    TitleAsc,
+    TitleDesc,
+    ModifiedNewest,
+    ModifiedOldest,
+    Manual,
+}
+
+/// Orders `notes` for display: by `mode`, then overdue notes first if
+/// asked, then pinned notes first.
+pub fn sorted_notes(
+    notes: &HashMap<String, Note>,
+    mode: SortMode,
+    overdue_first: bool,
+) -> Vec<&Note> {
+    let mut notes: Vec<&Note> = notes.values().collect();
+    match mode {
+        SortMode::TitleAsc => notes.sort_by_key(|note| (note.title.to_lowercase(), &note.id)),
+        SortMode::TitleDesc => {
+            notes.sort_by_key(|note| (note.title.to_lowercase(), &note.id));
+            notes.reverse();
+        }
+        SortMode::ModifiedNewest => {
+            notes.sort_by_key(|note| (std::cmp::Reverse(note.updated_at), &note.id))
+        }
+        SortMode::ModifiedOldest => notes.sort_by_key(|note| (note.updated_at, &note.id)),
+        SortMode::Manual => notes.sort_by_key(|note| (note.order, &note.id)),
+    }
+    // Stable sorts, so each group keeps the order chosen above.
+    if overdue_first {
+        let now = Utc::now();
+        notes.sort_by_key(|note| !note.is_overdue(now));
+    }
+    notes.sort_by_key(|note| !note.pinned);
+    notes
+}
+
+/// How long ago a note was modified, for the headers between groups of
+/// notes when sorted by date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bucket {
+    Today,
+    Yesterday,
+    LastSevenDays,
+    Older,
+}
+
+impl Bucket {
+    pub fn label(self) -> &'static str {
+        match self {
+            Bucket::Today => "Today",
+            Bucket::Yesterday => "Yesterday",
+            Bucket::LastSevenDays => "Last 7 days",
+            Bucket::Older => "Older",
+        }
+    }
+}
+
+/// Which `Bucket` `ts` falls in, counting calendar days in local time.
+/// Times after `now` count as today.
+pub fn date_bucket(ts: DateTime<Utc>, now: DateTime<Utc>) -> Bucket {
+    let day = ts.with_timezone(&Local).date_naive();
+    let today = now.with_timezone(&Local).date_naive();
+    match (today - day).num_days() {
+        ..=0 => Bucket::Today,
+        1 => Bucket::Yesterday,
+        2..=6 => Bucket::LastSevenDays,
+        _ => Bucket::Older,
+    }
+}
+
+/// A note's text as it was before an edit, for `Message::Undo`/`Message::Redo`.
+/// Pins, colors and the like aren't kept, so undoing text leaves them be.
+#[derive(Debug, Clone)]
+struct NotesSnapshot {
+    id: String,
+    title: String,
+    title_is_manual: bool,
+    content: String,
+    revision: u64,
+    updated_at: DateTime<Utc>,
+    last_edit: Instant,
+}
+
+impl NotesSnapshot {
+    fn of(note: &Note, last_edit: Instant) -> Self {
+        Self {
+            id: note.id.clone(),
+            title: note.title.clone(),
+            title_is_manual: note.title_is_manual,
+            content: note.content.clone(),
+            revision: note.revision,
+            updated_at: note.updated_at,
+            last_edit,
+        }
+    }
+}
+
+/// What the app last wrote to the notes file, shared by every `SaveJob`.
+/// Holding the lock serializes writes.
+#[derive(Debug, Default)]
+struct Written {
+    /// The newest generation written so far, so an older copy can't land
+    /// after a newer one.
+    generation: u64,
+    /// `storage::fingerprint` of the file as last loaded or written, to
+    /// spot changes made by anything else.
+    fingerprint: Option<u64>,
+}
+
+impl Written {
+    /// Fails with `ChangedOnDisk` if the file at `path` isn't the one last
+    /// loaded or written.
+    fn check(&self, path: &Path) -> Result<(), storage::ChangedOnDisk> {
+        if storage::fingerprint(path) == self.fingerprint {
+            Ok(())
+        } else {
+            Err(storage::ChangedOnDisk)
+        }
+    }
+}
+
+/// Where a deleted note was open, so undoing the deletion can put it back.
+#[derive(Debug, Clone, Copy)]
+struct DeletedNote {
+    /// Its position among the editor tabs, if it had one.
+    tab: Option<usize>,
+    was_current: bool,
+}
+
+/// An encrypted note unlocked this session.
+#[derive(Clone)]
+struct Unlocked {
+    /// Derived once on unlock, so writing the note doesn't run Argon2.
+    key: Arc<crypto::SealingKey>,
+    /// The content sealed last and what it sealed to, reused while the
+    /// note is unchanged.
+    sealed: Arc<Mutex<(String, String)>>,
+}
+
+impl Unlocked {
+    fn seal(&self, content: &str) -> Result<String, crypto::CryptoError> {
+        let mut sealed = self.sealed.lock().unwrap_or_else(PoisonError::into_inner);
+        if sealed.0 != content {
+            *sealed = (content.to_string(), self.key.seal(content)?);
+        }
+        Ok(sealed.1.clone())
+    }
+}
+
+/// Encrypts the notes in `notes` that were unlocked this session again.
+fn seal_unlocked(
+    notes: &mut HashMap<String, Note>,
+    unlocked: &HashMap<String, Unlocked>,
+) -> Result<(), crypto::CryptoError> {
+    for (id, unlocked) in unlocked {
+        if let Some(note) = notes.get_mut(id) {
+            note.content = unlocked.seal(&note.content)?;
+            note.encrypted = true;
+        }
+    }
+    Ok(())
+}
+
+/// A copy of the notes taken for one write of the notes file, so the write
+/// can happen off the UI thread.
+struct SaveJob {
+    path: PathBuf,
+    /// Unlocked notes are still in plain text here; `run` encrypts them.
+    /// Notes unchanged since the last job are shared with it, not copied.
+    notes: HashMap<String, Arc<Note>>,
+    unlocked: HashMap<String, Unlocked>,
+    generation: u64,
+    written: Arc<Mutex<Written>>,
+}
+
+impl SaveJob {
+    /// Backs up the old file and writes the notes, unless a newer job got
+    /// there first or the file changed on disk since it was last written.
+    fn run(self) -> Result<Option<String>, Arc<io::Error>> {
+        let sealed = self
+            .unlocked
+            .iter()
+            .filter_map(|(id, unlocked)| {
+                let note = self.notes.get(id)?;
+                Some(unlocked.seal(&note.content).map(|content| Note {
+                    content,
+                    encrypted: true,
+                    ..Note::clone(note)
+                }))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| Arc::new(io::Error::other(e)))?;
+        let notes = self
+            .notes
+            .values()
+            .filter(|note| !self.unlocked.contains_key(&note.id))
+            .map(|note| &**note)
+            .chain(&sealed);
+        let mut written = self.written.lock().unwrap_or_else(PoisonError::into_inner);
+        if written.generation >= self.generation {
+            return Ok(None);
+        }
+        written
+            .check(&self.path)
+            .map_err(|e| Arc::new(io::Error::other(e)))?;
+        let warning = storage::rotate_backups(&self.path)
+            .err()
+            .map(|e| format!("Could not back up {}: {}", self.path.display(), e));
+        storage::write_notes(&self.path, notes).map_err(|e| {
+            Arc::new(match e.downcast::<io::Error>() {
+                Ok(e) => *e,
+                Err(e) => io::Error::other(e.to_string()),
+            })
+        })?;
+        written.generation = self.generation;
+        written.fingerprint = storage::fingerprint(&self.path);
+        Ok(warning)
+    }
+
+    async fn run_in_background(self) -> Result<Option<String>, Arc<io::Error>> {
+        tokio::task::spawn_blocking(move || self.run())
+            .await
+            .unwrap_or_else(|e| Err(Arc::new(io::Error::other(e))))
+    }
+}
+
+pub struct NotesApp {
+    /// The notes file loaded on startup and auto-saved to.
+    storage_path: PathBuf,
+    /// Set when `storage_path` refused a write for lack of permission.
+    /// Autosave stops retrying until another location is chosen.
+    storage_read_only: bool,
+    /// The id of another running instance holding the notes file's lock.
+    /// Nothing is saved while it's set, unless the user takes the lock over.
+    locked_by: Option<u32>,
+    notes: HashMap<String, Note>,
+    trash: HashMap<String, Note>,
+    show_trash: bool,
+    /// The active tab; edits go to this note.
+    current_note: Option<String>,
+    /// Ids of the notes open in editor tabs, left to right.
+    open_tabs: Vec<String>,
+    editor: text_editor::Content,
+    /// Editors of notes switched away from this session, so the cursor
+    /// (and with it the scroll position) is where it was on return.
+    parked_editors: HashMap<String, text_editor::Content>,
+    /// Notes moved to the trash while their "Undo" toast is still up, and
+    /// where they were open.
+    recently_deleted: HashMap<String, DeletedNote>,
+    /// The notes file picked for import, waiting on "Replace" or "Merge".
+    pending_import: Option<PathBuf>,
+    /// Files are being dragged over the window.
+    drop_hover: bool,
+    /// The notes as they were before the last replacing import, while its
+    /// "Undo Import" toast is still up.
+    import_backup: Option<PathBuf>,
+    /// The sidebar note whose title is being edited in place.
+    renaming: Option<String>,
+    rename_input: String,
+    /// The sidebar note under the cursor and since when, and whether its
+    /// peek is showing.
+    hovered_note: Option<(String, Instant)>,
+    peek: bool,
+    /// The note clicked last and when, to spot a double-click.
+    last_click: Option<(String, Instant)>,
+    open_menu: Option<Menu>,
+    palette: Option<Palette>,
+    find_bar: Option<FindBar>,
+    /// Set when the window was asked to close with edits not yet on disk.
+    confirm_quit: bool,
+    sort_mode: SortMode,
+    search_query: String,
+    search_index: search::SearchIndex,
+    link_index: links::LinkIndex,
+    /// The note "Find Similar" last ran for, with its closest matches.
+    similar: Option<(String, Vec<(String, f32)>)>,
+    similarity_threshold: f32,
+    tag_filter: Option<String>,
+    favorites_only: bool,
+    due_soon_only: bool,
+    overdue_first: bool,
+    due_input: String,
+    color_filter: Option<NoteColor>,
+    tag_input: String,
+    selected_notebook: Option<String>,
+    notebook_input: String,
+    preview: bool,
+    show_settings: bool,
+    /// Focus mode: just the current note's editor, centered.
+    zen: bool,
+    /// Whether the word-frequency panel under the editor is expanded.
+    show_insights: bool,
+    /// Whether the "Linked from" panel under the editor is expanded.
+    show_backlinks: bool,
+    /// A note whose title is being typed and what it was before, so links
+    /// to the old title can be offered an update once the edit is over.
+    retitled: Option<(String, String)>,
+    /// Encrypted notes unlocked this session, so their content can be
+    /// encrypted again whenever they are written to disk.
+    unlocked: HashMap<String, Unlocked>,
+    /// The notes as last handed to a `SaveJob`, so the next one only has
+    /// to copy the notes that changed since.
+    snapshot: HashMap<String, Arc<Note>>,
+    lock_prompt: Option<String>,
+    password_input: String,
+    unsaved_changes: bool,
+    /// Set while a `SaveJob` runs in the background.
+    saving: bool,
+    save_generation: u64,
+    written: Arc<Mutex<Written>>,
+    /// Set when the notes file changed on disk under us. Saving stops until
+    /// the user overwrites it or reloads from it.
+    disk_changed: bool,
+    /// Whether the overwrite/reload prompt for `disk_changed` is showing.
+    conflict_prompt: bool,
+    /// The note and content the Markdown preview last rendered, for notes
+    /// past `PREVIEW_LIVE_LIMIT`.
+    preview_snapshot: Option<(String, String)>,
+    /// When `edit_current_note` last ran; saving waits for a pause in edits.
+    last_edit: Instant,
+    unsaved_preferences: bool,
+    dark_mode: bool,
+    /// The sidebar's share of the sidebar + editor width.
+    split_ratio: f32,
+    dragging_divider: bool,
+    last_divider_press: Option<Instant>,
+    /// Whether New Note reopens an existing blank note instead of adding one.
+    reuse_blank_notes: bool,
+    /// Whether sidebar entries show a line of content under the title.
+    show_snippets: bool,
+    sidebar_style: SidebarStyle,
+    /// Seconds of quiet before edits are saved; 0 saves only on request.
+    autosave_interval_secs: u64,
+    /// The version the "What's new" panel was last dismissed in.
+    last_seen_version: Option<String>,
+    show_whats_new: bool,
+    window: Option<WindowGeometry>,
+    font_size: u16,
+    undo_stack: Vec<NotesSnapshot>,
+    redo_stack: Vec<NotesSnapshot>,
+    toasts: Vec<Toast>,
+    /// Toasts pushed since the last update whose dismissal timers still
+    /// need starting.
+    unscheduled_toasts: usize,
+}
+
+impl Application for NotesApp {
+    type Executor = iced::executor::Default;
+    type Message = Message;
+    type Theme = Theme;
+    /// A notes file to use instead of the default one.
+    type Flags = Option<PathBuf>;
+
+    fn new(notes_path: Option<PathBuf>) -> (Self, Command<Message>) {
+        let preferences = storage::load_preferences();
+        let loaded = match &notes_path {
+            Some(path) => storage::load_notes_from(path),
+            None => storage::load_notes(),
+        };
+        let storage_path = notes_path.unwrap_or_else(storage::storage_path);
+        let fingerprint = storage::fingerprint(&storage_path);
+        // If the lock can't be written, neither can the notes; the
+        // read-only banner covers that once a save fails.
+        let locked_by = storage::acquire_lock(&storage_path).unwrap_or(None);
+        let (notes, error) = match loaded {
+            Ok(notes) => (notes, None),
+            Err(e) if storage::is_not_found(e.as_ref()) => (HashMap::new(), None),
+            Err(e) => (
+                HashMap::new(),
+                Some(format!(
+                    "Could not load {}, starting empty: {}",
+                    storage_path.display(),
+                    e
+                )),
+            ),
+        };
+        let (notes, trash) = trash::split(notes);
+        let mut search_index = search::SearchIndex::default();
+        search_index.sync(&notes);
+        let mut link_index = links::LinkIndex::default();
+        link_index.sync(&notes);
+        // The note open last session may have been deleted or lost since.
+        let current_note = preferences.current_note.filter(|id| notes.contains_key(id));
+        let mut open_tabs: Vec<String> = preferences
+            .open_tabs
+            .into_iter()
+            .filter(|id| notes.contains_key(id))
+            .collect();
+        if let Some(id) = &current_note {
+            if !open_tabs.contains(id) {
+                open_tabs.push(id.clone());
+            }
+        }
+        let editor = match current_note.as_ref().and_then(|id| notes.get(id)) {
+            Some(note) if !note.encrypted => text_editor::Content::with_text(&note.content),
+            _ => text_editor::Content::new(),
+        };
+        let mut app = Self {
+            storage_path,
+            storage_read_only: false,
+            locked_by,
+            notes,
+            trash,
+            show_trash: false,
+            current_note,
+            open_tabs,
+            editor,
+            parked_editors: HashMap::new(),
+            recently_deleted: HashMap::new(),
+            pending_import: None,
+            drop_hover: false,
+            import_backup: None,
+            renaming: None,
+            rename_input: String::new(),
+            last_click: None,
+            hovered_note: None,
+            peek: false,
+            open_menu: None,
+            palette: None,
+            find_bar: None,
+            confirm_quit: false,
+            sort_mode: preferences.sort_mode,
+            search_query: String::new(),
+            search_index,
+            link_index,
+            similar: None,
+            similarity_threshold: preferences.similarity_threshold.clamp(0.0, 1.0),
+            tag_filter: None,
+            favorites_only: false,
+            due_soon_only: false,
+            overdue_first: false,
+            due_input: String::new(),
+            color_filter: None,
+            tag_input: String::new(),
+            selected_notebook: None,
+            notebook_input: String::new(),
+            preview: false,
+            show_settings: false,
+            zen: false,
+            show_insights: false,
+            show_backlinks: false,
+            retitled: None,
+            unlocked: HashMap::new(),
+            snapshot: HashMap::new(),
+            lock_prompt: None,
+            password_input: String::new(),
+            unsaved_changes: false,
+            saving: false,
+            save_generation: 0,
+            written: Arc::new(Mutex::new(Written {
+                generation: 0,
+                fingerprint,
+            })),
+            disk_changed: false,
+            conflict_prompt: false,
+            preview_snapshot: None,
+            last_edit: Instant::now(),
+            unsaved_preferences: false,
+            dark_mode: preferences.dark_mode,
+            reuse_blank_notes: preferences.reuse_blank_notes,
+            show_snippets: preferences.show_snippets,
+            sidebar_style: preferences.sidebar_style,
+            autosave_interval_secs: preferences
+                .autosave_interval_secs
+                .min(MAX_AUTOSAVE_INTERVAL_SECS),
+            show_whats_new: whats_new::is_newer(
+                whats_new::CURRENT_VERSION,
+                preferences.last_seen_version.as_deref(),
+            ),
+            last_seen_version: preferences.last_seen_version,
+            split_ratio: preferences
+                .split_ratio
+                .clamp(MIN_SPLIT_RATIO, MAX_SPLIT_RATIO),
+            dragging_divider: false,
+            last_divider_press: None,
+            window: preferences.window,
+            font_size: preferences.font_size.clamp(MIN_FONT_SIZE, MAX_FONT_SIZE),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            toasts: Vec::new(),
+            unscheduled_toasts: 0,
+        };
+        if let Some(error) = error {
+            app.push_toast(Severity::Error, error);
+        }
+        let expire = expire_toasts(std::mem::take(&mut app.unscheduled_toasts));
+        (app, expire)
+    }
+
+    fn title(&self) -> String {
+        String::from("Multi-Notes App")
+    }
+
+    fn update(&mut self, message: Message) -> Command<Message> {
+        let command = self.handle_message(message);
+        self.search_index.sync(&self.notes);
+        self.link_index.sync(&self.notes);
+        let expire = expire_toasts(std::mem::take(&mut self.unscheduled_toasts));
+        Command::batch([command, expire])
+    }
+
+    fn theme(&self) -> Theme {
+        if self.dark_mode {
+            Theme::Dark
+        } else {
+            Theme::Light
+        }
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let autosave_notes = self.unsaved_changes
+            && !self.storage_read_only
+            && self.locked_by.is_none()
+            && !self.disk_changed
+            && self.autosave_interval_secs > 0;
+        let autosave = if autosave_notes || self.unsaved_preferences {
+            time::every(SAVE_POLL_INTERVAL).map(|_| Message::FlushSave)
+        } else {
+            Subscription::none()
+        };
+        Subscription::batch([
+            autosave,
+            time::every(CLOCK_INTERVAL).map(|_| Message::Tick),
+            event::listen_with(handle_event),
+            if self.dragging_divider {
+                event::listen_with(handle_divider_drag)
+            } else {
+                Subscription::none()
+            },
+        ])
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        if self.zen {
+            return self.view_zen();
+        }
+
+        let note_editor = if let Some(id) = &self.current_note {
+            if let Some(note) = self.notes.get(id) {
+                let (words, characters) = if note.encrypted {
+                    (0, 0)
+                } else {
+                    note_stats(&note.content)
+                };
+                column![
+                    self.view_header(note),
+                    self.view_content(note),
+                    text(format!("{} words, {} characters", words, characters)).size(12),
+                    self.view_insights(note),
+                    self.view_backlinks(note),
+                    text(format!(
+                        "edited {} · rev {}",
+                        humanize_age(note.updated_at, Utc::now()),
+                        note.revision
+                    ))
+                    .size(12)
+                    .style(Color::from_rgb(0.5, 0.5, 0.5)),
+                    row![
+                        button("Red").on_press(Message::ChangeNoteColor(NoteColor::Red)),
+                        button("Green").on_press(Message::ChangeNoteColor(NoteColor::Green)),
+                        button("Blue").on_press(Message::ChangeNoteColor(NoteColor::Blue)),
+                        button("Yellow").on_press(Message::ChangeNoteColor(NoteColor::Yellow)),
+                        button("Orange").on_press(Message::ChangeNoteColor(NoteColor::Orange)),
+                    ]
+                    .spacing(5),
+                    view_color_sliders(note.color),
+                    self.view_tags(note),
+                    self.view_notebook_picker(note),
+                    self.view_due(note),
+                    row![
+                        button(if note.pinned { "Unpin" } else { "Pin" })
+                            .on_press(Message::TogglePin(id.clone())),
+                        button(if note.favorite {
+                            "★ Starred"
+                        } else {
+                            "☆ Star"
+                        })
+                        .on_press(Message::ToggleFavorite(id.clone())),
+                        button(if note.locked {
+                            "Unlock Edits"
+                        } else {
+                            "Lock Edits"
+                        })
+                        .on_press(Message::ToggleLocked(id.clone())),
+                        button(if note.monospace {
+                            "Proportional"
+                        } else {
+                            "Monospace"
+                        })
+                        .on_press(Message::ToggleMonospace(id.clone())),
+                        button("Duplicate").on_press(Message::DuplicateNote(id.clone())),
+                        button("Find Similar").on_press(Message::FindSimilar(id.clone())),
+                        button("Copy").on_press(Message::CopyNote(id.clone())),
+                        button("Copy as Markdown").on_press(Message::CopyNoteMarkdown(id.clone())),
+                        button(if self.preview {
+                            "Hide Preview"
+                        } else {
+                            "Preview"
+                        })
+                        .on_press(Message::TogglePreview),
+                        self.view_lock_controls(note),
+                    ]
+                    .spacing(5),
+                ]
+                .spacing(10)
+                .height(Length::Fill)
+            } else {
+                column![text("Note not found")]
+            }
+        } else {
+            column![text("Select a note to edit")]
+        };
+
+        // FillPortion only takes integers, so split a thousand parts.
+        let sidebar_portion = (self.split_ratio * 1000.0).round() as u16;
+        let divider = mouse_area(
+            container(vertical_rule(2))
+                .width(DIVIDER_WIDTH)
+                .height(Length::Fill)
+                .center_x(),
+        )
+        .on_press(Message::PressDivider);
+        let content: Element<'_, Message> = if self.show_settings {
+            self.view_settings()
+        } else {
+            row![
+                self.view_notebooks(),
+                container(self.view_sidebar()).width(Length::FillPortion(sidebar_portion)),
+                divider,
+                column![self.view_tabs(), note_editor]
+                    .spacing(10)
+                    .width(Length::FillPortion(1000 - sidebar_portion)),
+            ]
+            .spacing(PANE_SPACING)
+            .height(Length::Fill)
+            .into()
+        };
+
+        let controls = row![
+            button("New Note").on_press(Message::CreateNote),
+            button("Import").on_press(Message::ImportNotes),
+            button("Import Folder").on_press(Message::ImportDirectory),
+            button("Export").on_press(Message::ExportNotes),
+            button("Export Markdown").on_press(Message::ExportCurrentMarkdown),
+            button("Export HTML").on_press(Message::ExportHtml),
+            button(if self.show_trash { "Notes" } else { "Trash" }).on_press(Message::ToggleTrash),
+            button(if self.dark_mode {
+                "Light Mode"
+            } else {
+                "Dark Mode"
+            })
+            .on_press(Message::ToggleTheme),
+            button("Reset Zoom").on_press(Message::ResetZoom),
+            button("Settings").on_press(Message::OpenSettings),
+        ]
+        .spacing(10)
+        .align_items(iced::Alignment::Center);
+
+        let mut layout = column![self.view_menu_bar()]
+            .padding(WINDOW_PADDING)
+            .spacing(20);
+        if let Some(palette) = &self.palette {
+            layout = layout.push(
+                container(palette::view(palette, self.palette_matches()))
+                    .width(Length::Fill)
+                    .center_x(),
+            );
+        }
+        if self.show_whats_new {
+            layout = layout.push(whats_new::view());
+        }
+        layout = layout.push(content);
+
+        if self.storage_read_only {
+            let name = self
+                .storage_path
+                .file_name()
+                .map_or_else(|| NOTES_FILE.into(), |name| name.to_string_lossy());
+            layout = layout.push(
+                row![
+                    text(format!("{} is not writable — choose a new location?", name))
+                        .style(Color::from_rgb(0.8, 0.0, 0.0)),
+                    button("Choose Location…").on_press(Message::ChooseStorageLocation),
+                ]
+                .spacing(10)
+                .align_items(iced::Alignment::Center),
+            );
+        }
+
+        if let Some(pid) = self.locked_by {
+            let name = self
+                .storage_path
+                .file_name()
+                .map_or_else(|| NOTES_FILE.into(), |name| name.to_string_lossy());
+            layout = layout.push(
+                row![
+                    text(format!(
+                        "{} is open in another window (process {}), so changes here aren't saved",
+                        name, pid
+                    ))
+                    .style(Color::from_rgb(0.8, 0.0, 0.0)),
+                    button("Edit Anyway").on_press(Message::TakeOverLock),
+                ]
+                .spacing(10)
+                .align_items(iced::Alignment::Center),
+            );
+        }
+
+        if self.conflict_prompt {
+            let name = self
+                .storage_path
+                .file_name()
+                .map_or_else(|| NOTES_FILE.into(), |name| name.to_string_lossy());
+            layout = layout.push(
+                row![
+                    text(format!("{} changed on disk —", name)),
+                    button("Overwrite").on_press(Message::OverwriteDisk),
+                    button("Reload").on_press(Message::ReloadFromDisk),
+                    button("Cancel").on_press(Message::CancelConflict),
+                ]
+                .spacing(10)
+                .align_items(iced::Alignment::Center),
+            );
+        }
+
+        if self.confirm_quit {
+            layout = layout.push(
+                row![
+                    text("Save before quitting?"),
+                    button("Save").on_press(Message::SaveAndQuit),
+                    button("Discard").on_press(Message::DiscardAndQuit),
+                    button("Cancel").on_press(Message::CancelQuit),
+                ]
+                .spacing(10),
+            );
+        }
+
+        if let Some(path) = &self.pending_import {
+            layout = layout.push(
+                row![
+                    text(format!("Import notes from {}?", path.display())),
+                    button("Replace").on_press(Message::ImportReplace),
+                    button("Merge").on_press(Message::ImportMerge),
+                    button("Cancel").on_press(Message::CancelImport),
+                ]
+                .spacing(10),
+            );
+        }
+
+        layout = layout.push(controls);
+
+        if !self.toasts.is_empty() {
+            layout = layout.push(toast::view(&self.toasts));
+        }
+
+        let window = container(layout)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x();
+        if self.drop_hover {
+            window
+                .style(iced::theme::Container::Custom(Box::new(DropZone)))
+                .into()
+        } else {
+            window.into()
+        }
+    }
+}
+
+impl NotesApp {
+    fn handle_message(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::ToggleMenu(menu) => {
+                self.open_menu = if self.open_menu == Some(menu) {
+                    None
+                } else {
+                    Some(menu)
+                };
+            }
+            Message::MenuItem(message) => {
+                self.open_menu = None;
+                return self.handle_message(*message);
+            }
+            Message::TogglePalette => {
+                if self.palette.take().is_none() {
+                    self.palette = Some(Palette::default());
+                    return text_input::focus(palette::input_id());
+                }
+            }
+            Message::UpdatePalette(query) => {
+                if let Some(palette) = &mut self.palette {
+                    palette.query = query;
+                    palette.selected = 0;
+                }
+            }
+            Message::SubmitPalette => {
+                let Some(palette) = &self.palette else {
+                    return Command::none();
+                };
+                let chosen = self.palette_matches().into_iter().nth(palette.selected);
+                if let Some((_, message)) = chosen {
+                    return self.handle_message(Message::RunCommand(Box::new(message)));
+                }
+            }
+            Message::RunCommand(message) => {
+                self.palette = None;
+                return self.handle_message(*message);
+            }
+            Message::CreateNote => {
+                if self.reuse_blank_notes {
+                    let blank = self
+                        .notes
+                        .values()
+                        .find(|note| note.is_blank() && note.notebook == self.selected_notebook);
+                    if let Some(blank) = blank {
+                        let id = blank.id.clone();
+                        self.open_note(Some(id));
+                        return focus_title();
+                    }
+                }
+                let mut note = Note::new(DEFAULT_TITLE, String::new());
+                note.notebook = self.selected_notebook.clone();
+                note.order = self.next_order();
+                let id = note.id.clone();
+                self.notes.insert(id.clone(), note);
+                self.open_note(Some(id));
+                self.unsaved_changes = true;
+                return focus_title();
+            }
+            Message::SelectNote(id) => {
+                let now = Instant::now();
+                let double_click = self.last_click.as_ref().is_some_and(|(last, at)| {
+                    *last == id && now.duration_since(*at) < DOUBLE_CLICK_INTERVAL
+                });
+                if double_click {
+                    self.last_click = None;
+                    return self.handle_message(Message::BeginRename(id));
+                }
+                self.last_click = Some((id.clone(), now));
+                self.open_note(Some(id));
+            }
+            Message::BeginRename(id) => {
+                let Some(note) = self.notes.get(&id).filter(|note| !note.locked) else {
+                    return Command::none();
+                };
+                self.rename_input = note.title.clone();
+                self.renaming = Some(id);
+                return Command::batch([
+                    text_input::focus(rename_input_id()),
+                    text_input::select_all(rename_input_id()),
+                ]);
+            }
+            Message::UpdateRename(title) => {
+                self.rename_input = title;
+            }
+            Message::CommitRename => {
+                let title = std::mem::take(&mut self.rename_input);
+                if let Some(id) = self.renaming.take() {
+                    if !title.trim().is_empty() {
+                        if self.current_note.as_ref() != Some(&id) {
+                            self.open_note(Some(id.clone()));
+                        }
+                        let old = self.notes.get(&id).map(|note| note.title.clone());
+                        let new = title.trim().to_string();
+                        self.edit_current_note(|note| {
+                            note.title = new.clone();
+                            note.title_is_manual = true;
+                        });
+                        if let Some(old) = old {
+                            self.offer_link_update(old, new);
+                        }
+                    }
+                }
+            }
+            Message::Escape => {
+                if self.palette.is_some() {
+                    self.palette = None;
+                } else if self.find_bar.is_some() {
+                    self.find_bar = None;
+                } else if self.zen {
+                    self.zen = false;
+                } else if self.show_settings {
+                    self.show_settings = false;
+                } else if self.renaming.is_some() {
+                    self.renaming = None;
+                    self.rename_input.clear();
+                } else if !self.search_query.is_empty() {
+                    self.search_query.clear();
+                    // iced drops focus from a text input on Escape by itself.
+                }
+            }
+            Message::FocusSearch => {
+                return Command::batch([
+                    text_input::focus(search_input_id()),
+                    text_input::select_all(search_input_id()),
+                ]);
+            }
+            Message::SelectPrevious => match &mut self.palette {
+                Some(palette) => palette.selected = palette.selected.saturating_sub(1),
+                None => self.select_adjacent(-1),
+            },
+            Message::SelectNext => {
+                let count = self.palette.as_ref().map(|_| self.palette_matches().len());
+                match (&mut self.palette, count) {
+                    (Some(palette), Some(count)) => {
+                        palette.selected = (palette.selected + 1).min(count.saturating_sub(1));
+                    }
+                    _ => self.select_adjacent(1),
+                }
+            }
+            Message::DuplicateNote(id) => {
+                if let Some(original) = self.notes.get(&id) {
+                    let copy_id = uuid::Uuid::new_v4().to_string();
+                    let now = Utc::now();
+                    let copy = Note {
+                        id: copy_id.clone(),
+                        title: format!("{} (copy)", original.title),
+                        order: self.next_order(),
+                        created_at: now,
+                        updated_at: now,
+                        revision: 0,
+                        ..original.clone()
+                    };
+                    self.notes.insert(copy_id.clone(), copy);
+                    self.open_note(Some(copy_id));
+                    self.unsaved_changes = true;
+                }
+            }
+            Message::UpdateNoteTitle(title) => {
+                if self.current_is_locked() {
+                    return Command::none();
+                }
+                if let Some(note) = self.current_note.as_ref().and_then(|id| self.notes.get(id)) {
+                    if self.retitled.as_ref().is_none_or(|(id, _)| *id != note.id) {
+                        self.retitled = Some((note.id.clone(), note.title.clone()));
+                    }
+                }
+                self.edit_current_note(|note| {
+                    note.title = title;
+                    note.title_is_manual = true;
+                });
+            }
+            Message::CommitTitle => {
+                if let Some(id) = self.current_note.clone() {
+                    self.commit_title(&id);
+                }
+            }
+            Message::EditContent(action) => {
+                let is_edit = action.is_edit();
+                if is_edit && self.current_is_locked() {
+                    return Command::none();
+                }
+                self.editor.perform(action);
+                if is_edit {
+                    let content = editor_text(&self.editor);
+                    self.edit_current_note(|note| note.set_content(content));
+                }
+            }
+            Message::ChangeNoteColor(color) => {
+                self.edit_current_note(|note| note.color = color.to_rgb());
+            }
+            Message::CycleNoteColor => {
+                let Some(note) = self.current_note.as_ref().and_then(|id| self.notes.get(id))
+                else {
+                    return Command::none();
+                };
+                // A custom color isn't in the cycle, so it moves to the first preset.
+                let next = NoteColor::ALL
+                    .into_iter()
+                    .find(|color| color.to_rgb() == note.color)
+                    .map_or(NoteColor::ALL[0], |color| color.next());
+                return self.handle_message(Message::ChangeNoteColor(next));
+            }
+            Message::SetCustomColor(r, g, b) => {
+                self.edit_current_note(|note| note.color = [r, g, b]);
+            }
+            Message::ToggleChecklistItem(line) => {
+                let content = self
+                    .current_note
+                    .as_ref()
+                    .and_then(|id| self.notes.get(id))
+                    .filter(|note| !note.encrypted && !note.locked)
+                    .map(|note| checklist::toggle(&note.content, line));
+                if let Some(content) = content {
+                    self.replace_current_content(content);
+                }
+            }
+            Message::ToggleTask(offset) => {
+                let content = self
+                    .current_note
+                    .as_ref()
+                    .and_then(|id| self.notes.get(id))
+                    .filter(|note| !note.encrypted && !note.locked)
+                    .and_then(|note| checklist::toggle_at(&note.content, offset));
+                if let Some(content) = content {
+                    self.replace_current_content(content);
+                }
+            }
+            Message::ToggleZen => {
+                self.zen = !self.zen;
+                self.show_settings = false;
+            }
+            Message::ToggleFindBar => {
+                if self.find_bar.take().is_none() {
+                    self.find_bar = Some(FindBar::default());
+                    return text_input::focus(replace::find_input_id());
+                }
+            }
+            Message::UpdateFind(find) => {
+                if let Some(bar) = &mut self.find_bar {
+                    bar.find = find;
+                    bar.next = 0;
+                }
+            }
+            Message::UpdateReplace(replacement) => {
+                if let Some(bar) = &mut self.find_bar {
+                    bar.replace = replacement;
+                }
+            }
+            Message::ToggleMatchCase(case_sensitive) => {
+                if let Some(bar) = &mut self.find_bar {
+                    bar.case_sensitive = case_sensitive;
+                }
+            }
+            Message::Replace => {
+                let Some(bar) = &self.find_bar else {
+                    return Command::none();
+                };
+                let replaced = self
+                    .current_note
+                    .as_ref()
+                    .and_then(|id| self.notes.get(id))
+                    .filter(|note| !note.encrypted && !note.locked)
+                    .and_then(|note| bar.replace_next(&note.content));
+                match replaced {
+                    Some((content, next)) => {
+                        self.replace_current_content(content);
+                        if let Some(bar) = &mut self.find_bar {
+                            bar.next = next;
+                        }
+                    }
+                    None => {
+                        let message = format!("No matches for '{}'", bar.find);
+                        self.push_toast(Severity::Info, message);
+                    }
+                }
+            }
+            Message::ReplaceAll => {
+                let Some(bar) = &self.find_bar else {
+                    return Command::none();
+                };
+                let replaced = self
+                    .current_note
+                    .as_ref()
+                    .and_then(|id| self.notes.get(id))
+                    .filter(|note| !note.encrypted && !note.locked)
+                    .map(|note| {
+                        replace::replace_all(
+                            &note.content,
+                            &bar.find,
+                            &bar.replace,
+                            bar.case_sensitive,
+                        )
+                    });
+                match replaced {
+                    Some((content, count @ 1..)) => {
+                        self.replace_current_content(content);
+                        let message = match count {
+                            1 => "Replaced 1 match".to_string(),
+                            _ => format!("Replaced {} matches", count),
+                        };
+                        self.push_toast(Severity::Success, message);
+                    }
+                    Some(_) => {
+                        let message = format!("No matches for '{}'", bar.find);
+                        self.push_toast(Severity::Info, message);
+                    }
+                    None => {}
+                }
+            }
+            Message::Undo => {
+                if self
+                    .undo_stack
+                    .last()
+                    .is_some_and(|snapshot| self.is_locked(&snapshot.id))
+                {
+                    self.push_toast(Severity::Info, "Unlock the note to undo its edits");
+                } else if let Some(snapshot) = self.undo_stack.pop() {
+                    let redo = self.restore_snapshot(snapshot);
+                    self.redo_stack.extend(redo);
+                }
+            }
+            Message::Redo => {
+                if self
+                    .redo_stack
+                    .last()
+                    .is_some_and(|snapshot| self.is_locked(&snapshot.id))
+                {
+                    self.push_toast(Severity::Info, "Unlock the note to redo its edits");
+                } else if let Some(snapshot) = self.redo_stack.pop() {
+                    let undo = self.restore_snapshot(snapshot);
+                    self.undo_stack.extend(undo);
+                }
+            }
+            Message::TogglePin(id) => {
+                if let Some(note) = self.notes.get_mut(&id) {
+                    note.pinned = !note.pinned;
+                    self.unsaved_changes = true;
+                }
+            }
+            Message::ToggleFavorite(id) => {
+                if let Some(note) = self.notes.get_mut(&id) {
+                    note.favorite = !note.favorite;
+                    self.unsaved_changes = true;
+                }
+            }
+            Message::ToggleLocked(id) => {
+                if let Some(note) = self.notes.get_mut(&id) {
+                    note.locked = !note.locked;
+                    self.unsaved_changes = true;
+                }
+                if self.renaming.as_ref() == Some(&id) {
+                    self.renaming = None;
+                }
+            }
+            Message::ToggleMonospace(id) => {
+                if let Some(note) = self.notes.get_mut(&id) {
+                    note.monospace = !note.monospace;
+                    self.unsaved_changes = true;
+                }
+            }
+            Message::MoveNoteUp(id) => self.move_note(&id, -1),
+            Message::MoveNoteDown(id) => self.move_note(&id, 1),
+            Message::RequestDelete(id) => self.request_delete(id),
+            Message::DeleteSelected => {
+                if let Some(id) = self.current_note.clone() {
+                    self.request_delete(id);
+                }
+            }
+            Message::UndoDelete(id) => {
+                self.toasts.retain(|toast| match &toast.action {
+                    Some((_, Message::UndoDelete(other))) => *other != id,
+                    _ => true,
+                });
+                let Some(deleted) = self.recently_deleted.remove(&id) else {
+                    return Command::none();
+                };
+                // Gone for good if the trash was emptied in the meantime.
+                if let Some(mut note) = self.trash.remove(&id) {
+                    note.trashed_at = None;
+                    self.notes.insert(id.clone(), note);
+                    self.unsaved_changes = true;
+                    if let Some(tab) = deleted.tab {
+                        let tab = tab.min(self.open_tabs.len());
+                        self.open_tabs.insert(tab, id.clone());
+                        self.unsaved_preferences = true;
+                    }
+                    if deleted.was_current {
+                        self.open_note(Some(id));
+                    }
+                }
+            }
+            Message::CloseRequested => {
+                // With autosave on, pending edits would be written shortly
+                // anyway, so write them now rather than ask. Only a save
+                // that can't happen leaves something to ask about.
+                if self.autosave_interval_secs > 0 {
+                    self.save_now();
+                }
+                if !self.unsaved_changes {
+                    return self.quit();
+                }
+                self.confirm_quit = true;
+            }
+            Message::SaveAndQuit => {
+                self.confirm_quit = false;
+                let error = self.save_job().and_then(|job| self.finish_save(job.run()));
+                match error {
+                    Some(e) => self.push_toast(
+                        Severity::Error,
+                        format!("Could not save before quitting: {}", e),
+                    ),
+                    None if !self.unsaved_changes => return self.quit(),
+                    None if self.disk_changed => self.conflict_prompt = true,
+                    None if self.locked_by.is_some() => self.push_toast(
+                        Severity::Info,
+                        "Another window has these notes open; choose Edit Anyway to save here",
+                    ),
+                    // The notes file is read-only and the banner says so.
+                    None => {}
+                }
+            }
+            Message::DiscardAndQuit => return self.quit(),
+            Message::CancelQuit => {
+                self.confirm_quit = false;
+            }
+            Message::RestoreNote(id) => {
+                if let Some(mut note) = self.trash.remove(&id) {
+                    note.trashed_at = None;
+                    self.notes.insert(id, note);
+                    self.unsaved_changes = true;
+                }
+            }
+            Message::EmptyTrash => {
+                if !self.trash.is_empty() {
+                    self.trash.clear();
+                    self.unsaved_changes = true;
+                }
+            }
+            Message::ToggleTrash => {
+                self.show_trash = !self.show_trash;
+            }
+            Message::SetSortMode(mode) => {
+                self.sort_mode = mode;
+                if let Err(e) = self.save_preferences() {
+                    self.push_toast(Severity::Error, e.to_string());
+                }
+            }
+            Message::UpdateSearch(query) => {
+                self.search_query = query;
+            }
+            Message::FilterByTag(tag) => {
+                self.tag_filter = tag;
+            }
+            Message::FilterByColor(color) => {
+                self.color_filter = color;
+            }
+            Message::ToggleDueSoonFilter => {
+                self.due_soon_only = !self.due_soon_only;
+            }
+            Message::ToggleOverdueFirst => {
+                self.overdue_first = !self.overdue_first;
+            }
+            Message::UpdateDueInput(value) => {
+                self.due_input = value;
+            }
+            Message::SubmitDue => match parse_due(&self.due_input) {
+                Some(due) => return self.handle_message(Message::SetDue(Some(due))),
+                None => self.push_toast(
+                    Severity::Info,
+                    "Enter the due date as YYYY-MM-DD or YYYY-MM-DD HH:MM",
+                ),
+            },
+            Message::SetDue(due) => {
+                self.due_input = due.map(format_due).unwrap_or_default();
+                self.edit_current_note(|note| note.due = due);
+            }
+            Message::ToggleFavoritesFilter => {
+                self.favorites_only = !self.favorites_only;
+            }
+            Message::UpdateTagInput(value) => {
+                self.tag_input = value;
+            }
+            Message::AddTag(tag) => {
+                let tag = tag.trim().to_string();
+                self.tag_input.clear();
+                let is_new = self
+                    .current_note
+                    .as_ref()
+                    .and_then(|id| self.notes.get(id))
+                    .is_some_and(|note| !note.tags.contains(&tag));
+                if !tag.is_empty() && is_new {
+                    self.edit_current_note(|note| note.tags.push(tag));
+                }
+            }
+            Message::RemoveTag(tag) => {
+                self.edit_current_note(|note| note.tags.retain(|existing| *existing != tag));
+            }
+            Message::SelectNotebook(notebook) => {
+                self.selected_notebook = notebook;
+            }
+            Message::MoveNoteToNotebook(id, notebook) => {
+                let notebook = notebook
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty());
+                if let Some(note) = self.notes.get_mut(&id) {
+                    note.notebook = notebook;
+                    self.unsaved_changes = true;
+                }
+                self.notebook_input.clear();
+            }
+            Message::UpdateNotebookInput(value) => {
+                self.notebook_input = value;
+            }
+            Message::SelectTab(id) => {
+                if self.notes.contains_key(&id) {
+                    self.open_note(Some(id));
+                }
+            }
+            Message::CloseTab(id) => self.close_tab(&id),
+            Message::CopyNote(id) => {
+                if let Some(content) = self.copyable(&id).map(|note| note.content.clone()) {
+                    self.push_toast(Severity::Success, "Copied!");
+                    return iced::clipboard::write(content);
+                }
+            }
+            Message::CopyNoteMarkdown(id) => {
+                if let Some(markdown) = self
+                    .copyable(&id)
+                    .map(|note| format!("# {}\n\n{}", note.title, note.content))
+                {
+                    self.push_toast(Severity::Success, "Copied as Markdown!");
+                    return iced::clipboard::write(markdown);
+                }
+            }
+            Message::HoverNote(id) => {
+                self.peek = false;
+                self.hovered_note = id.clone().map(|id| (id, Instant::now()));
+                if let Some(id) = id {
+                    return Command::perform(tokio::time::sleep(PEEK_DELAY), move |_| {
+                        Message::ShowPeek(id)
+                    });
+                }
+            }
+            Message::ShowPeek(id) => {
+                // Only if the cursor stayed on the same note all along; an
+                // earlier visit's timer may fire during a later one.
+                self.peek = self.hovered_note.as_ref().is_some_and(|(hovered, since)| {
+                    *hovered == id && since.elapsed() >= PEEK_DELAY
+                });
+            }
+            Message::FindSimilar(id) => self.find_similar(id),
+            Message::CloseSimilar => {
+                self.similar = None;
+            }
+            Message::SetSimilarityThreshold(threshold) => {
+                self.similarity_threshold = threshold.clamp(0.0, 1.0);
+                self.unsaved_preferences = true;
+                if let Some((id, _)) = self.similar.take() {
+                    self.find_similar(id);
+                }
+            }
+            Message::ToggleInsights => {
+                self.show_insights = !self.show_insights;
+            }
+            Message::ToggleBacklinks => {
+                self.show_backlinks = !self.show_backlinks;
+            }
+            Message::RetargetLinks(old, new) => {
+                self.toasts
+                    .retain(|toast| !matches!(toast.action, Some((_, Message::RetargetLinks(..)))));
+                let sources: Vec<String> = self
+                    .link_index
+                    .backlinks(&old)
+                    .map(str::to_string)
+                    .collect();
+                let now = Utc::now();
+                let mut updated = 0;
+                for id in sources {
+                    let Some(note) = self.notes.get_mut(&id).filter(|note| !note.locked) else {
+                        continue;
+                    };
+                    let content = links::retarget(&note.content, &old, &new);
+                    if content != note.content {
+                        if self.current_note.as_ref() == Some(&id) {
+                            self.editor = text_editor::Content::with_text(&content);
+                        }
+                        note.content = content;
+                        note.updated_at = now;
+                        note.revision += 1;
+                        updated += 1;
+                    }
+                }
+                if updated > 0 {
+                    self.unsaved_changes = true;
+                    let message = format!("Updated links in {}", count_notes(updated));
+                    self.push_toast(Severity::Success, message);
+                }
+            }
+            Message::OpenUrl(url) => {
+                if let Err(e) = open::that(&url) {
+                    self.push_toast(Severity::Error, format!("Could not open {}: {}", url, e));
+                }
+            }
+            Message::FollowLink(title) => {
+                match self.link_index.resolve(&title).map(str::to_string) {
+                    Some(id) => self.open_note(Some(id)),
+                    None => {
+                        self.push_toast(Severity::Info, format!("No note titled '{}'", title));
+                        if let Some(toast) = self.toasts.last_mut() {
+                            toast.action = Some(("Create", Message::CreateLinkedNote(title)));
+                        }
+                    }
+                }
+            }
+            Message::CreateLinkedNote(title) => {
+                self.toasts.retain(|toast| match &toast.action {
+                    Some((_, Message::CreateLinkedNote(other))) => *other != title,
+                    _ => true,
+                });
+                // It may have been created some other way since the offer.
+                if let Some(id) = self.link_index.resolve(&title).map(str::to_string) {
+                    self.open_note(Some(id));
+                    return Command::none();
+                }
+                let mut note = Note::new(title, String::new());
+                note.title_is_manual = true;
+                note.notebook = self.selected_notebook.clone();
+                note.order = self.next_order();
+                let id = note.id.clone();
+                self.notes.insert(id.clone(), note);
+                self.open_note(Some(id));
+                self.unsaved_changes = true;
+            }
+            Message::TogglePreview => {
+                self.preview = !self.preview;
+                if self.preview {
+                    return self.handle_message(Message::RefreshPreview);
+                }
+            }
+            Message::LockNote(id) => {
+                if let Some(unlocked) = self.unlocked.remove(&id) {
+                    if let Err(e) = self.encrypt_note(&id, &unlocked.key) {
+                        self.push_toast(Severity::Error, format!("Could not lock note: {}", e));
+                    }
+                } else {
+                    self.lock_prompt = Some(id);
+                    self.password_input.clear();
+                }
+            }
+            Message::UpdatePassword(password) => {
+                self.password_input = password;
+            }
+            Message::ConfirmLock => {
+                let password = std::mem::take(&mut self.password_input);
+                if let Some(id) = self.lock_prompt.take() {
+                    if password.is_empty() {
+                        self.push_toast(Severity::Info, "Enter a password to lock the note");
+                        self.lock_prompt = Some(id);
+                    } else if let Err(e) = crypto::SealingKey::new(&password)
+                        .and_then(|key| self.encrypt_note(&id, &key))
+                    {
+                        self.push_toast(Severity::Error, format!("Could not lock note: {}", e));
+                    }
+                }
+            }
+            Message::CancelLock => {
+                self.lock_prompt = None;
+                self.password_input.clear();
+            }
+            Message::UnlockNote(id, password) => {
+                self.password_input.clear();
+                if let Some(note) = self.notes.get_mut(&id) {
+                    match crypto::decrypt(&note.content, &password) {
+                        Ok((content, key)) => {
+                            // Until it's edited, the note seals to what it was.
+                            let sealed = std::mem::replace(&mut note.content, content.clone());
+                            note.encrypted = false;
+                            let unlocked = Unlocked {
+                                key: Arc::new(key),
+                                sealed: Arc::new(Mutex::new((content, sealed))),
+                            };
+                            self.unlocked.insert(id.clone(), unlocked);
+                            if self.current_note.as_ref() == Some(&id) {
+                                self.open_note(Some(id));
+                            }
+                        }
+                        Err(e) => {
+                            let message = format!("Could not unlock '{}': {}", note.title, e);
+                            self.push_toast(Severity::Error, message);
+                        }
+                    }
+                }
+            }
+            Message::ToggleReuseBlankNotes(reuse) => {
+                self.reuse_blank_notes = reuse;
+                if let Err(e) = self.save_preferences() {
+                    self.push_toast(Severity::Error, e.to_string());
+                }
+            }
+            Message::OpenSettings => {
+                self.show_settings = true;
+                self.open_menu = None;
+            }
+            Message::CloseSettings => {
+                self.show_settings = false;
+            }
+            Message::RestoreDefaultSettings => {
+                // Window geometry and open notes are session state, not
+                // settings, so they are left alone.
+                let defaults = Preferences::default();
+                self.sort_mode = defaults.sort_mode;
+                self.dark_mode = defaults.dark_mode;
+                self.reuse_blank_notes = defaults.reuse_blank_notes;
+                self.show_snippets = defaults.show_snippets;
+                self.sidebar_style = defaults.sidebar_style;
+                self.autosave_interval_secs = defaults.autosave_interval_secs;
+                self.similarity_threshold = defaults.similarity_threshold;
+                self.split_ratio = defaults.split_ratio;
+                self.font_size = defaults.font_size;
+                self.unsaved_preferences = true;
+            }
+            Message::DismissWhatsNew => {
+                self.show_whats_new = false;
+                self.last_seen_version = Some(whats_new::CURRENT_VERSION.to_string());
+                if let Err(e) = self.save_preferences() {
+                    self.push_toast(Severity::Error, e.to_string());
+                }
+            }
+            Message::SetAutosaveInterval(secs) => {
+                self.autosave_interval_secs = secs.min(MAX_AUTOSAVE_INTERVAL_SECS);
+                self.unsaved_preferences = true;
+            }
+            Message::SetSidebarStyle(style) => {
+                self.sidebar_style = style;
+                self.unsaved_preferences = true;
+            }
+            Message::ToggleSnippets(show) => {
+                self.show_snippets = show;
+                self.unsaved_preferences = true;
+            }
+            Message::ToggleTheme => {
+                self.dark_mode = !self.dark_mode;
+                if let Err(e) = self.save_preferences() {
+                    self.push_toast(Severity::Error, e.to_string());
+                }
+            }
+            Message::IncreaseFont => self.set_font_size(self.font_size.saturating_add(1)),
+            Message::DecreaseFont => self.set_font_size(self.font_size.saturating_sub(1)),
+            Message::ResetZoom => self.set_font_size(DEFAULT_FONT_SIZE),
+            Message::ImportNotes
+            | Message::ImportReplace
+            | Message::UndoImport
+            | Message::ImportMerge
+            | Message::CancelImport
+            | Message::FilesHovered
+            | Message::FilesHoveredLeft
+            | Message::FileDropped(_)
+            | Message::ImportDirectory
+            | Message::ExportNotes
+            | Message::ExportVisible
+            | Message::ExportCurrentMarkdown
+            | Message::ExportArchive
+            | Message::ExportHtml => return self.handle_transfer(message),
+            Message::ChooseStorageLocation => {
+                let file = rfd::FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .set_file_name(NOTES_FILE)
+                    .save_file();
+                if let Some(path) = file {
+                    // The save dialog already asked before replacing a file.
+                    self.lock_written().fingerprint = storage::fingerprint(&path);
+                    if self.locked_by.is_none() {
+                        let _ = storage::release_lock(&self.storage_path);
+                    }
+                    self.locked_by = storage::acquire_lock(&path).unwrap_or(None);
+                    self.storage_path = path;
+                    self.storage_read_only = false;
+                    self.disk_changed = false;
+                    self.conflict_prompt = false;
+                    self.unsaved_changes = true;
+                    self.save_now();
+                    if !self.unsaved_changes {
+                        let message =
+                            format!("Saving notes to {}", resolved(&self.storage_path).display());
+                        self.push_toast(Severity::Success, message);
+                    }
+                }
+            }
+            Message::FlushSave => {
+                let paused = self.last_edit.elapsed();
+                if paused >= PREFERENCES_DEBOUNCE {
+                    self.flush_preferences();
+                }
+                if self.autosave_interval_secs > 0
+                    && paused >= Duration::from_secs(self.autosave_interval_secs)
+                {
+                    return self.save_in_background();
+                }
+            }
+            Message::WindowUnfocused => {
+                self.flush_preferences();
+                if self.autosave_interval_secs > 0 {
+                    return self.save_in_background();
+                }
+            }
+            Message::SaveCurrent => {
+                if let Some(id) = self.current_note.clone() {
+                    self.commit_title(&id);
+                }
+                match self.save_current_note() {
+                    Ok(Some(title)) => {
+                        self.push_toast(Severity::Success, format!("Saved '{}'", title));
+                    }
+                    Ok(None) => {}
+                    Err(e) if is_read_only(e.as_ref()) => self.storage_read_only = true,
+                    Err(e) if e.is::<storage::ChangedOnDisk>() => {
+                        self.disk_changed = true;
+                        self.conflict_prompt = true;
+                    }
+                    Err(e) => self.push_toast(Severity::Error, e.to_string()),
+                }
+            }
+            Message::TakeOverLock => match storage::take_lock(&self.storage_path) {
+                Ok(()) => self.locked_by = None,
+                Err(e) => {
+                    let message =
+                        format!("Could not take over {}: {}", self.storage_path.display(), e);
+                    self.push_toast(Severity::Error, message);
+                }
+            },
+            Message::OverwriteDisk => {
+                self.conflict_prompt = false;
+                self.disk_changed = false;
+                self.lock_written().fingerprint = storage::fingerprint(&self.storage_path);
+                self.save_now();
+            }
+            Message::ReloadFromDisk => {
+                self.conflict_prompt = false;
+                match self.reload_from_disk() {
+                    Ok(()) => self.push_toast(
+                        Severity::Success,
+                        "Reloaded notes from disk, keeping newer edits made here",
+                    ),
+                    Err(e) => {
+                        let message = format!("Could not reload notes: {}", e);
+                        self.push_toast(Severity::Error, message);
+                    }
+                }
+            }
+            Message::CancelConflict => {
+                // Edits stay unsaved; the next explicit save asks again.
+                self.conflict_prompt = false;
+            }
+            Message::SaveComplete(result) => {
+                self.saving = false;
+                if let Some(e) = self.finish_save(result) {
+                    self.push_toast(Severity::Error, e);
+                }
+            }
+            Message::RefreshPreview => {
+                if let Some(note) = self.current_note.as_ref().and_then(|id| self.notes.get(id)) {
+                    self.preview_snapshot = Some((note.id.clone(), note.content.clone()));
+                }
+            }
+            Message::PressDivider => {
+                let now = Instant::now();
+                if self
+                    .last_divider_press
+                    .is_some_and(|at| now.duration_since(at) < DOUBLE_CLICK_INTERVAL)
+                {
+                    self.last_divider_press = None;
+                    return self.handle_message(Message::ResizeSplit(DEFAULT_SPLIT_RATIO));
+                }
+                self.last_divider_press = Some(now);
+                self.dragging_divider = true;
+            }
+            Message::DragDivider(x) => {
+                // Until the first resize event says otherwise, the window is
+                // the size it opened at, which is iced's default when no
+                // geometry was saved.
+                let width = self
+                    .window
+                    .map_or(WindowGeometry::default().width, |window| window.width)
+                    as f32;
+                // Everything left of the sidebar, and everything in the row
+                // that belongs to neither the sidebar nor the editor.
+                let offset = WINDOW_PADDING + NOTEBOOK_RAIL_WIDTH + PANE_SPACING;
+                let fixed =
+                    2.0 * WINDOW_PADDING + NOTEBOOK_RAIL_WIDTH + 3.0 * PANE_SPACING + DIVIDER_WIDTH;
+                let available = width - fixed;
+                if available > 0.0 {
+                    return self.handle_message(Message::ResizeSplit((x - offset) / available));
+                }
+            }
+            Message::ReleaseDivider => {
+                self.dragging_divider = false;
+            }
+            Message::ResizeSplit(ratio) => {
+                self.split_ratio = ratio.clamp(MIN_SPLIT_RATIO, MAX_SPLIT_RATIO);
+                self.unsaved_preferences = true;
+            }
+            Message::WindowResized(width, height) => {
+                let geometry = self.window.get_or_insert_with(WindowGeometry::default);
+                geometry.width = width;
+                geometry.height = height;
+                self.unsaved_preferences = true;
+            }
+            Message::WindowMoved(x, y) => {
+                let geometry = self.window.get_or_insert_with(WindowGeometry::default);
+                geometry.position = Some((x, y));
+                self.unsaved_preferences = true;
+            }
+            Message::Dismiss(index) => {
+                if index < self.toasts.len() {
+                    self.toasts.remove(index);
+                }
+                self.discard_stale_import_backup();
+                self.forget_expired_deletes();
+            }
+            Message::Tick => {}
+            Message::ExpireToasts => {
+                self.toasts
+                    .retain(|toast| toast.spawned.elapsed() < TOAST_DURATION);
+                self.discard_stale_import_backup();
+                self.forget_expired_deletes();
+            }
+        }
+        Command::none()
+    }
+
+    fn view_menu_bar(&self) -> Element<'_, Message> {
+        let titles = Menu::ALL.into_iter().fold(row![].spacing(5), |row, menu| {
+            row.push(
+                button(text(menu.title()).size(14))
+                    .on_press(Message::ToggleMenu(menu))
+                    .style(if self.open_menu == Some(menu) {
+                        ButtonTheme::Primary
+                    } else {
+                        ButtonTheme::Text
+                    }),
+            )
+        });
+        let Some(open) = self.open_menu else {
+            return titles.into();
+        };
+
+        let items = open.items().into_iter().fold(column![], |column, item| {
+            let shortcut = item
+                .shortcut
+                .map(|shortcut| shortcut.label())
+                .unwrap_or_default();
+            column.push(
+                button(row![
+                    text(item.label).size(14).width(Length::Fill),
+                    text(shortcut)
+                        .size(14)
+                        .style(Color::from_rgb(0.5, 0.5, 0.5)),
+                ])
+                .on_press(Message::MenuItem(Box::new(item.message)))
+                .style(ButtonTheme::Text)
+                .width(220),
+            )
+        });
+        column![
+            titles,
+            container(items)
+                .padding(5)
+                .style(iced::theme::Container::Box),
+        ]
+        .spacing(5)
+        .into()
+    }
+
+    fn view_sidebar(&self) -> Element<'_, Message> {
+        let visible = self.visible_notes();
+
+        let notes_list = if self.show_trash {
+            trash::view(&self.trash, self.font_size)
+        } else {
+            let by_date = matches!(
+                self.sort_mode,
+                SortMode::ModifiedNewest | SortMode::ModifiedOldest
+            );
+            let now = Utc::now();
+            let mut previous_bucket = None;
+            visible
+                .iter()
+                .fold(column![].spacing(5), |mut column, note| {
+                    if by_date {
+                        let bucket = date_bucket(note.updated_at, now);
+                        if previous_bucket != Some(bucket) {
+                            previous_bucket = Some(bucket);
+                            column = column.push(
+                                text(bucket.label())
+                                    .size(12)
+                                    .style(Color::from_rgb(0.5, 0.5, 0.5)),
+                            );
+                        }
+                    }
+                    let mut title = text(note_label(note)).size(self.font_size);
+                    if note.is_blank() {
+                        title = title.style(Color::from_rgb(0.55, 0.55, 0.55));
+                    }
+                    let mut heading = row![title].spacing(5);
+                    if note.is_overdue(now) {
+                        heading = heading.push(
+                            text("⏰")
+                                .size(self.font_size)
+                                .style(Color::from_rgb(0.8, 0.0, 0.0)),
+                        );
+                    }
+                    let mut label = column![heading];
+                    if self.show_snippets && !note.encrypted {
+                        label = label.push(view_snippet(&note.content, &self.search_query));
+                    } else if !self.search_query.is_empty()
+                        && !contains_ignore_case(&note.title, &self.search_query)
+                    {
+                        label = label.push(text("…matched in body…").size(12));
+                    }
+                    let entry: Element<'_, Message> = if self.renaming.as_ref() == Some(&note.id) {
+                        text_input("Title", &self.rename_input)
+                            .id(rename_input_id())
+                            .on_input(Message::UpdateRename)
+                            .on_submit(Message::CommitRename)
+                            .size(self.font_size)
+                            .padding(10)
+                            .into()
+                    } else {
+                        self.note_button(note, label)
+                    };
+                    let entry = if note.pinned && !note.encrypted {
+                        self.view_peek(note, entry)
+                    } else {
+                        entry
+                    };
+                    let mut item = row![entry].spacing(5);
+                    if self.sort_mode == SortMode::Manual {
+                        item = item.push(
+                            column![
+                                button(text("▲").size(10))
+                                    .on_press(Message::MoveNoteUp(note.id.clone())),
+                                button(text("▼").size(10))
+                                    .on_press(Message::MoveNoteDown(note.id.clone())),
+                            ]
+                            .spacing(2),
+                        );
+                    }
+                    column.push(
+                        item.push(
+                            button(if note.favorite { "★" } else { "☆" })
+                                .on_press(Message::ToggleFavorite(note.id.clone()))
+                                .style(ButtonTheme::Text)
+                                .padding(10),
+                        )
+                        .push(
+                            button("✕")
+                                .on_press(Message::RequestDelete(note.id.clone()))
+                                .padding(10),
+                        ),
+                    )
+                })
+        };
+
+        let sort_controls = self.view_sort_controls();
+
+        let search = text_input("Search", &self.search_query)
+            .id(search_input_id())
+            .on_input(Message::UpdateSearch)
+            .padding(5);
+
+        let due_soon_filter = button(text("⏰ Due soon").size(12))
+            .on_press(Message::ToggleDueSoonFilter)
+            .style(if self.due_soon_only {
+                ButtonTheme::Primary
+            } else {
+                ButtonTheme::Secondary
+            });
+        let overdue_first = button(text("Overdue first").size(12))
+            .on_press(Message::ToggleOverdueFirst)
+            .style(if self.overdue_first {
+                ButtonTheme::Primary
+            } else {
+                ButtonTheme::Secondary
+            });
+
+        let favorites_filter = button(text("★ Favorites").size(12))
+            .on_press(Message::ToggleFavoritesFilter)
+            .style(if self.favorites_only {
+                ButtonTheme::Primary
+            } else {
+                ButtonTheme::Secondary
+            });
+
+        let all_tags: BTreeSet<&String> = self.notes.values().flat_map(|note| &note.tags).collect();
+        let tag_filters = all_tags.into_iter().fold(
+            row![favorites_filter, due_soon_filter, overdue_first].spacing(5),
+            |row, tag| {
+                let active = self.tag_filter.as_ref() == Some(tag);
+                row.push(
+                    button(text(format!("#{}", tag)).size(12))
+                        .on_press(Message::FilterByTag(if active {
+                            None
+                        } else {
+                            Some(tag.clone())
+                        }))
+                        .style(if active {
+                            ButtonTheme::Primary
+                        } else {
+                            ButtonTheme::Secondary
+                        }),
+                )
+            },
+        );
+
+        let color_filters = NoteColor::ALL
+            .into_iter()
+            .fold(row![].spacing(5), |row, color| {
+                let active = self.color_filter == Some(color);
+                row.push(
+                    button(text(if active { "✓" } else { " " }).size(12))
+                        .on_press(Message::FilterByColor(if active {
+                            None
+                        } else {
+                            Some(color)
+                        }))
+                        .style(ButtonTheme::Custom(Box::new(NoteButtonStyle(
+                            color.to_rgb(),
+                        ))))
+                        .width(24),
+                )
+            });
+
+        let count = if visible.len() == self.notes.len() {
+            count_notes(self.notes.len())
+        } else {
+            format!("showing {} of {}", visible.len(), self.notes.len())
+        };
+
+        let list: Element<'_, Message> = if !self.show_trash && self.notes.is_empty() {
+            container(text("No notes yet — click New Note to start").size(14))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .center_x()
+                .center_y()
+                .into()
+        } else {
+            scrollable(notes_list).height(Length::Fill).into()
+        };
+
+        let mut sidebar = column![
+            color_filters,
+            text(count).size(12),
+            search,
+            tag_filters,
+            sort_controls
+        ]
+        .spacing(10);
+        if let Some(similar) = self.view_similar() {
+            sidebar = sidebar.push(similar);
+        }
+        sidebar.push(list).into()
+    }
+
+    /// Whether the current note is locked against edits.
+    fn current_is_locked(&self) -> bool {
+        self.current_note
+            .as_ref()
+            .is_some_and(|id| self.is_locked(id))
+    }
+
+    /// Whether note `id` is locked against edits.
+    fn is_locked(&self, id: &str) -> bool {
+        self.notes.get(id).is_some_and(|note| note.locked)
+    }
+
+    /// A note's sidebar entry, colored as `sidebar_style` says.
+    fn note_button<'a>(
+        &self,
+        note: &Note,
+        label: iced::widget::Column<'a, Message>,
+    ) -> Element<'a, Message> {
+        let entry = match self.sidebar_style {
+            SidebarStyle::FullBackground => button(label)
+                .style(ButtonTheme::Custom(Box::new(NoteButtonStyle(note.color))))
+                .padding(10),
+            SidebarStyle::AccentStripe => button(row![
+                container(horizontal_space())
+                    .width(4)
+                    .height(Length::Fill)
+                    .style(iced::theme::Container::Custom(Box::new(AccentStripe(
+                        note.color
+                    )))),
+                container(label).padding(10),
+            ])
+            .style(ButtonTheme::Custom(Box::new(NeutralNoteButtonStyle)))
+            .padding(0),
+        };
+        entry
+            .on_press(Message::SelectNote(note.id.clone()))
+            .width(Length::Fill)
+            .into()
+    }
+
+    /// Tracks the cursor over a sidebar entry and, once `PEEK_DELAY` has
+    /// passed, floats the first lines of the note beside it.
+    fn view_peek<'a>(&self, note: &'a Note, entry: Element<'a, Message>) -> Element<'a, Message> {
+        let peeking = self.peek
+            && self
+                .hovered_note
+                .as_ref()
+                .is_some_and(|(id, _)| *id == note.id);
+        let entry: Element<'a, Message> = if peeking {
+            let lines: Vec<String> = note
+                .content
+                .lines()
+                .take(PEEK_LINES)
+                .map(|line| truncate_display(line, PEEK_LINE_LEN))
+                .collect();
+            let peek = container(text(lines.join("\n")).size(12))
+                .padding(10)
+                .max_width(320)
+                .style(iced::theme::Container::Box);
+            tooltip(entry, peek, tooltip::Position::FollowCursor).into()
+        } else {
+            entry
+        };
+        mouse_area(entry)
+            .on_enter(Message::HoverNote(Some(note.id.clone())))
+            .on_exit(Message::HoverNote(None))
+            .into()
+    }
+
+    /// The ranked matches from the last "Find Similar", if it's open.
+    fn view_similar(&self) -> Option<Element<'_, Message>> {
+        let (id, matches) = self.similar.as_ref()?;
+        let title = self.notes.get(id).map_or("", |note| note.title.as_str());
+        let header = row![
+            text(format!("Similar to '{}'", title))
+                .size(14)
+                .width(Length::Fill),
+            button(text("✕").size(12))
+                .on_press(Message::CloseSimilar)
+                .style(ButtonTheme::Text),
+        ]
+        .align_items(iced::Alignment::Center);
+
+        let list = matches
+            .iter()
+            .filter_map(|(id, score)| Some((self.notes.get(id)?, score)))
+            .fold(column![header].spacing(5), |list, (note, score)| {
+                list.push(
+                    button(text(format!("{} — {:.0}%", note_label(note), score * 100.0)).size(12))
+                        .on_press(Message::SelectNote(note.id.clone()))
+                        .style(ButtonTheme::Text)
+                        .width(Length::Fill),
+                )
+            });
+        let list = if matches.is_empty() {
+            list.push(
+                text(format!(
+                    "No notes overlap by {:.0}% or more",
+                    self.similarity_threshold * 100.0
+                ))
+                .size(12),
+            )
+        } else {
+            list
+        };
+        Some(
+            container(list)
+                .padding(10)
+                .style(iced::theme::Container::Box)
+                .into(),
+        )
+    }
+
+    /// Every action in the menus, then every note to jump to, ranked by
+    /// the palette's query.
+    fn palette_matches(&self) -> Vec<(String, Message)> {
+        let Some(palette) = &self.palette else {
+            return Vec::new();
+        };
+        palette::rank(&palette.query, palette::entries(&self.sorted_notes()))
+    }
+
+    /// The note `id` if its content can be copied, explaining why not
+    /// when it's locked.
+    fn copyable(&mut self, id: &str) -> Option<&Note> {
+        if self.notes.get(id)?.encrypted {
+            self.push_toast(Severity::Info, "Unlock the note to copy it");
+            return None;
+        }
+        self.notes.get(id)
+    }
+
+    /// Ranks the notes most like `id` for the sidebar's similar panel.
+    fn find_similar(&mut self, id: String) {
+        let mut matches = self.search_index.similar(&id, self.similarity_threshold);
+        matches.truncate(SIMILAR_LIMIT);
+        self.similar = Some((id, matches));
+    }
+
+    fn notebooks(&self) -> BTreeSet<&String> {
+        self.notes
+            .values()
+            .filter_map(|note| note.notebook.as_ref())
+            .collect()
+    }
+
+    fn view_sort_controls(&self) -> Element<'_, Message> {
+        [
+            ("A-Z", SortMode::TitleAsc),
+            ("Z-A", SortMode::TitleDesc),
+            ("Newest", SortMode::ModifiedNewest),
+            ("Oldest", SortMode::ModifiedOldest),
+            ("Manual", SortMode::Manual),
+        ]
+        .into_iter()
+        .fold(row![].spacing(5), |row, (label, mode)| {
+            let sort_button = button(text(label).size(12));
+            row.push(if mode == self.sort_mode {
+                sort_button
+            } else {
+                sort_button.on_press(Message::SetSortMode(mode))
+            })
+        })
+        .into()
+    }
+
+    /// Every preference in one pane, shown in place of the notes.
+    fn view_settings(&self) -> Element<'_, Message> {
+        let setting = |label: &'static str, control: Element<'static, Message>| {
+            row![text(label).width(160), control]
+                .spacing(10)
+                .align_items(iced::Alignment::Center)
+        };
+        let font_size = row![
+            button("−").on_press(Message::DecreaseFont),
+            text(self.font_size),
+            button("+").on_press(Message::IncreaseFont),
+        ]
+        .spacing(10)
+        .align_items(iced::Alignment::Center);
+
+        column![
+            text("Settings").size(24),
+            setting(
+                "Dark mode",
+                checkbox("", self.dark_mode)
+                    .on_toggle(|_| Message::ToggleTheme)
+                    .into(),
+            ),
+            setting("Font size", font_size.into()),
+            row![text("Sort notes by").width(160), self.view_sort_controls()]
+                .spacing(10)
+                .align_items(iced::Alignment::Center),
+            setting(
+                "Sidebar width",
+                slider(
+                    MIN_SPLIT_RATIO..=MAX_SPLIT_RATIO,
+                    self.split_ratio,
+                    Message::ResizeSplit
+                )
+                .step(0.01)
+                .width(200)
+                .into(),
+            ),
+            setting(
+                "Similarity threshold",
+                row![
+                    slider(
+                        0.05..=0.95,
+                        self.similarity_threshold,
+                        Message::SetSimilarityThreshold
+                    )
+                    .step(0.05)
+                    .width(200),
+                    text(format!("{:.0}%", self.similarity_threshold * 100.0)),
+                ]
+                .spacing(10)
+                .into(),
+            ),
+            setting(
+                "Autosave after",
+                row![
+                    slider(
+                        0.0..=MAX_AUTOSAVE_INTERVAL_SECS as f64,
+                        self.autosave_interval_secs as f64,
+                        |secs| Message::SetAutosaveInterval(secs.round() as u64),
+                    )
+                    .step(1.0)
+                    .width(200),
+                    text(match self.autosave_interval_secs {
+                        0 => "off, save manually".to_string(),
+                        1 => "1 second".to_string(),
+                        secs => format!("{} seconds", secs),
+                    }),
+                ]
+                .spacing(10)
+                .into(),
+            ),
+            setting(
+                "Note colors",
+                pick_list(
+                    SidebarStyle::ALL,
+                    Some(self.sidebar_style),
+                    Message::SetSidebarStyle
+                )
+                .into(),
+            ),
+            setting(
+                "Reuse blank notes",
+                checkbox("", self.reuse_blank_notes)
+                    .on_toggle(Message::ToggleReuseBlankNotes)
+                    .into(),
+            ),
+            setting(
+                "Show snippets",
+                checkbox("", self.show_snippets)
+                    .on_toggle(Message::ToggleSnippets)
+                    .into(),
+            ),
+            row![
+                button("Restore Defaults").on_press(Message::RestoreDefaultSettings),
+                button("Done").on_press(Message::CloseSettings),
+            ]
+            .spacing(10),
+        ]
+        .spacing(15)
+        .height(Length::Fill)
+        .into()
+    }
+
+    fn view_tabs(&self) -> Element<'_, Message> {
+        let tabs = self.open_tabs.iter().fold(row![].spacing(5), |tabs, id| {
+            let Some(note) = self.notes.get(id) else {
+                return tabs;
+            };
+            let active = self.current_note.as_ref() == Some(id);
+            tabs.push(
+                row![
+                    button(text(note_label(note)).size(14))
+                        .on_press(Message::SelectTab(id.clone()))
+                        .style(if active {
+                            ButtonTheme::Primary
+                        } else {
+                            ButtonTheme::Secondary
+                        }),
+                    button(text("✕").size(12))
+                        .on_press(Message::CloseTab(id.clone()))
+                        .style(ButtonTheme::Text),
+                ]
+                .align_items(iced::Alignment::Center),
+            )
+        });
+        scrollable(tabs)
+            .direction(scrollable::Direction::Horizontal(
+                scrollable::Properties::default(),
+            ))
+            .into()
+    }
+
+    fn view_notebooks(&self) -> Element<'_, Message> {
+        let entry = |label: String, notebook: Option<String>| {
+            let selected = self.selected_notebook == notebook;
+            button(text(label).size(14))
+                .on_press(Message::SelectNotebook(notebook))
+                .style(if selected {
+                    ButtonTheme::Primary
+                } else {
+                    ButtonTheme::Text
+                })
+                .width(Length::Fill)
+        };
+
+        let rail = column![entry(format!("All Notes ({})", self.notes.len()), None)].spacing(5);
+        let rail = self.notebooks().into_iter().fold(rail, |rail, notebook| {
+            let count = self
+                .notes
+                .values()
+                .filter(|note| note.notebook.as_ref() == Some(notebook))
+                .count();
+            rail.push(entry(
+                format!("{} ({})", notebook, count),
+                Some(notebook.clone()),
+            ))
+        });
+
+        scrollable(rail)
+            .width(NOTEBOOK_RAIL_WIDTH)
+            .height(Length::Fill)
+            .into()
+    }
+
+    fn view_notebook_picker<'a>(&'a self, note: &'a Note) -> Element<'a, Message> {
+        let notebooks: Vec<String> = self.notebooks().into_iter().cloned().collect();
+        let id = note.id.clone();
+
+        let mut picker = row![
+            text("Notebook").size(12),
+            pick_list(notebooks, note.notebook.clone(), move |notebook| {
+                Message::MoveNoteToNotebook(id.clone(), Some(notebook))
+            })
+            .placeholder("None")
+            .text_size(12),
+            text_input("New notebook", &self.notebook_input)
+                .on_input(Message::UpdateNotebookInput)
+                .on_submit(Message::MoveNoteToNotebook(
+                    note.id.clone(),
+                    Some(self.notebook_input.clone()),
+                ))
+                .padding(5)
+                .width(150),
+        ]
+        .spacing(10)
+        .align_items(iced::Alignment::Center);
+
+        if note.notebook.is_some() {
+            picker = picker.push(
+                button(text("Remove").size(12))
+                    .on_press(Message::MoveNoteToNotebook(note.id.clone(), None)),
+            );
+        }
+        picker.into()
+    }
+
+    fn view_insights(&self, note: &Note) -> Element<'_, Message> {
+        let label = if self.show_insights {
+            "▾ Insights"
+        } else {
+            "▸ Insights"
+        };
+        let toggle = button(text(label).size(12))
+            .style(ButtonTheme::Text)
+            .padding(0)
+            .on_press(Message::ToggleInsights);
+        // Only analyze while the panel is open; it walks the whole note.
+        if !self.show_insights || note.encrypted {
+            return toggle.into();
+        }
+
+        let insights = analyze(&note.content);
+        let top_words = if insights.top_words.is_empty() {
+            "none yet".to_string()
+        } else {
+            insights
+                .top_words
+                .iter()
+                .map(|(word, count)| format!("{} ({})", word, count))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        column![
+            toggle,
+            text(format!("Top words: {}", top_words)).size(12),
+            text(format!("About {} min read", insights.reading_minutes)).size(12),
+        ]
+        .spacing(5)
+        .into()
+    }
+
+    /// The title and first line of the note, kept above the content so
+    /// they stay in view however far the content is scrolled.
+    fn view_header<'a>(&'a self, note: &'a Note) -> Element<'a, Message> {
+        let mut title = text_input("Title", &note.title)
+            .id(title_input_id())
+            .size(self.font_size)
+            .padding(10);
+        // Without `on_input` the field is drawn disabled.
+        if !note.locked {
+            title = title
+                .on_input(Message::UpdateNoteTitle)
+                .on_submit(Message::CommitTitle);
+        }
+        let first_line = if note.encrypted {
+            None
+        } else {
+            note.content
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+        };
+        let mut header = column![title].spacing(5);
+        if let Some(line) = first_line {
+            header = header.push(
+                text(truncate_display(line, HEADER_LINE_LEN))
+                    .size(12)
+                    .font(note_font(note))
+                    .style(Color::from_rgb(0.5, 0.5, 0.5)),
+            );
+        }
+        container(header)
+            .padding(5)
+            .width(Length::Fill)
+            .style(iced::theme::Container::Box)
+            .into()
+    }
+
+    /// The notes linking to `note` by its title, when the panel is open.
+    fn view_backlinks(&self, note: &Note) -> Element<'_, Message> {
+        let sources: Vec<&Note> = self
+            .link_index
+            .backlinks(&note.title)
+            .filter(|id| *id != note.id)
+            .filter_map(|id| self.notes.get(id))
+            .collect();
+        let label = format!(
+            "{} Linked from ({})",
+            if self.show_backlinks { "▾" } else { "▸" },
+            sources.len()
+        );
+        let toggle = button(text(label).size(12))
+            .style(ButtonTheme::Text)
+            .padding(0)
+            .on_press(Message::ToggleBacklinks);
+        if !self.show_backlinks {
+            return toggle.into();
+        }
+
+        sources
+            .into_iter()
+            .fold(column![toggle].spacing(5), |list, source| {
+                list.push(
+                    button(text(note_label(source)).size(12))
+                        .on_press(Message::SelectNote(source.id.clone()))
+                        .style(ButtonTheme::Text)
+                        .padding(0),
+                )
+            })
+            .into()
+    }
+
+    fn view_due<'a>(&'a self, note: &'a Note) -> Element<'a, Message> {
+        let mut due = row![
+            text("Due").size(12),
+            text_input("YYYY-MM-DD HH:MM", &self.due_input)
+                .on_input(Message::UpdateDueInput)
+                .on_submit(Message::SubmitDue)
+                .padding(5)
+                .width(170),
+        ]
+        .spacing(10)
+        .align_items(iced::Alignment::Center);
+
+        if let Some(when) = note.due {
+            let now = Utc::now();
+            let (label, color) = if note.is_overdue(now) {
+                ("⏰ overdue", Color::from_rgb(0.8, 0.0, 0.0))
+            } else if note.is_due_within(now, DUE_SOON) {
+                ("due soon", Color::from_rgb(0.8, 0.5, 0.0))
+            } else {
+                ("", Color::from_rgb(0.5, 0.5, 0.5))
+            };
+            due = due
+                .push(text(format_due(when)).size(12))
+                .push(text(label).size(12).style(color))
+                .push(button(text("Clear").size(12)).on_press(Message::SetDue(None)));
+        }
+        due.into()
+    }
+
+    fn view_tags<'a>(&'a self, note: &'a Note) -> Element<'a, Message> {
+        let chips = note.tags.iter().fold(row![].spacing(5), |row, tag| {
+            row.push(
+                button(text(format!("{} ✕", tag)).size(12))
+                    .on_press(Message::RemoveTag(tag.clone()))
+                    .style(ButtonTheme::Secondary),
+            )
+        });
+
+        row![
+            chips,
+            text_input("Add tag", &self.tag_input)
+                .on_input(Message::UpdateTagInput)
+                .on_submit(Message::AddTag(self.tag_input.clone()))
+                .padding(5)
+                .width(150),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    fn view_lock_controls<'a>(&'a self, note: &'a Note) -> Element<'a, Message> {
+        if self.lock_prompt.as_ref() == Some(&note.id) {
+            row![
+                text_input("Password", &self.password_input)
+                    .secure(true)
+                    .on_input(Message::UpdatePassword)
+                    .on_submit(Message::ConfirmLock)
+                    .width(150),
+                button("Lock").on_press(Message::ConfirmLock),
+                button("Cancel").on_press(Message::CancelLock),
+            ]
+            .spacing(5)
+            .into()
+        } else if note.encrypted {
+            row![].into()
+        } else {
+            button("🔒 Lock")
+                .on_press(Message::LockNote(note.id.clone()))
+                .into()
+        }
+    }
+
+    /// Focus mode's whole window: the current note's editor and a way back.
+    fn view_zen(&self) -> Element<'_, Message> {
+        let editor = match self.current_note.as_ref().and_then(|id| self.notes.get(id)) {
+            Some(note) => self.view_content(note),
+            None => text("Select a note to edit").into(),
+        };
+        let mut layout = column![
+            row![
+                horizontal_space(),
+                button(text("Exit Focus Mode").size(12))
+                    .on_press(Message::ToggleZen)
+                    .style(ButtonTheme::Text),
+            ],
+            container(container(editor).max_width(ZEN_MAX_WIDTH))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .center_x(),
+        ]
+        .padding(ZEN_PADDING)
+        .spacing(10);
+        if !self.toasts.is_empty() {
+            layout = layout.push(toast::view(&self.toasts));
+        }
+        layout.into()
+    }
+
+    fn view_content<'a>(&'a self, note: &'a Note) -> Element<'a, Message> {
+        if note.encrypted {
+            let unlock = Message::UnlockNote(note.id.clone(), self.password_input.clone());
+            return column![
+                text("🔒 This note is locked"),
+                row![
+                    text_input("Password", &self.password_input)
+                        .secure(true)
+                        .on_input(Message::UpdatePassword)
+                        .on_submit(unlock.clone())
+                        .width(200),
+                    button("Unlock").on_press(unlock),
+                ]
+                .spacing(5),
+            ]
+            .spacing(10)
+            .height(Length::Fill)
+            .into();
+        }
+
+        let font = note_font(note);
+        let mut editor = text_editor(&self.editor)
+            .font(font)
+            .padding(10)
+            .height(Length::Fill);
+        if !note.locked {
+            editor = editor.on_action(Message::EditContent);
+        }
+        let editor: Element<'a, Message> = if note.locked {
+            column![
+                row![
+                    text("🔒 This note is locked against edits").size(12),
+                    button(text("Unlock to edit").size(12))
+                        .on_press(Message::ToggleLocked(note.id.clone())),
+                ]
+                .spacing(10)
+                .align_items(iced::Alignment::Center),
+                editor,
+            ]
+            .spacing(5)
+            .height(Length::Fill)
+            .into()
+        } else {
+            editor.into()
+        };
+
+        let editor = match &self.find_bar {
+            Some(bar) => column![replace::view(bar, note.locked), editor]
+                .spacing(5)
+                .height(Length::Fill)
+                .into(),
+            None => editor,
+        };
+
+        let items = checklist::items(&note.content);
+        let editor = if items.is_empty() {
+            editor
+        } else {
+            let done = items.iter().filter(|item| item.checked).count();
+            let list = items.iter().fold(
+                column![text(format!("{}/{} done", done, items.len())).size(12)].spacing(5),
+                |list, item| {
+                    let line = item.line;
+                    list.push(
+                        checkbox(item.label, item.checked)
+                            .on_toggle(move |_| Message::ToggleChecklistItem(line)),
+                    )
+                },
+            );
+            column![editor, scrollable(list).height(Length::Shrink)]
+                .spacing(10)
+                .height(Length::Fill)
+                .into()
+        };
+
+        if self.preview {
+            let preview: Element<'a, Message> = if note.content.len() > PREVIEW_LIVE_LIMIT {
+                let snapshot = match &self.preview_snapshot {
+                    Some((id, content)) if *id == note.id => markdown::view(
+                        content,
+                        font,
+                        Message::OpenUrl,
+                        Message::FollowLink,
+                        Message::ToggleTask,
+                    ),
+                    _ => column![].into(),
+                };
+                column![
+                    row![
+                        text("Large note: the preview updates when refreshed").size(12),
+                        button(text("Refresh").size(12)).on_press(Message::RefreshPreview),
+                    ]
+                    .spacing(10)
+                    .align_items(iced::Alignment::Center),
+                    scrollable(snapshot).height(Length::Fill),
+                ]
+                .spacing(10)
+                .into()
+            } else {
+                scrollable(markdown::view(
+                    &note.content,
+                    font,
+                    Message::OpenUrl,
+                    Message::FollowLink,
+                    Message::ToggleTask,
+                ))
+                .into()
+            };
+            row![
+                editor,
+                container(preview).width(Length::Fill).height(Length::Fill),
+            ]
+            .spacing(10)
+            .height(Length::Fill)
+            .into()
+        } else {
+            editor
+        }
+    }
+
+    fn sorted_notes(&self) -> Vec<&Note> {
+        sorted_notes(&self.notes, self.sort_mode, self.overdue_first)
+    }
+
+    fn visible_notes(&self) -> Vec<&Note> {
+        let query = &self.search_query;
+        let now = Utc::now();
+        let matches = if query.is_empty() {
+            None
+        } else {
+            self.search_index.search(query)
+        };
+        let phrase = search::tokens(query).nth(1).is_some();
+        self.sorted_notes()
+            .into_iter()
+            .filter(|note| {
+                let contains_query = || {
+                    contains_ignore_case(&note.title, query)
+                        || (!note.encrypted && contains_ignore_case(&note.content, query))
+                };
+                match &matches {
+                    // The index finds the words anywhere; a phrase has to
+                    // appear as typed, which only the candidates are
+                    // scanned for.
+                    Some(ids) if phrase => ids.contains(&note.id) && contains_query(),
+                    Some(ids) => ids.contains(&note.id),
+                    None => contains_query(),
+                }
+            })
+            .filter(|note| {
+                self.tag_filter
+                    .as_ref()
+                    .is_none_or(|tag| note.tags.contains(tag))
+            })
+            .filter(|note| !self.favorites_only || note.favorite)
+            .filter(|note| !self.due_soon_only || note.is_due_within(now, DUE_SOON))
+            .filter(|note| {
+                self.color_filter
+                    .is_none_or(|color| note.color == color.to_rgb())
+            })
+            .filter(|note| {
+                self.selected_notebook
+                    .as_ref()
+                    .is_none_or(|notebook| note.notebook.as_ref() == Some(notebook))
+            })
+            .collect()
+    }
+
+    fn next_order(&self) -> i64 {
+        self.notes
+            .values()
+            .map(|note| note.order)
+            .max()
+            .map_or(0, |order| order + 1)
+    }
+
+    /// Swaps a note's manual position with its neighbour in the displayed
+    /// list, `offset` being -1 for up and 1 for down.
+    fn move_note(&mut self, id: &str, offset: isize) {
+        // Notes that predate manual ordering all share order 0; renumber
+        // them first so that swapping actually changes something.
+        let mut by_order: Vec<&Note> = self.notes.values().collect();
+        by_order.sort_by_key(|note| (note.order, &note.id));
+        let has_duplicates = by_order
+            .windows(2)
+            .any(|pair| pair[0].order == pair[1].order);
+        if has_duplicates {
+            let ids: Vec<String> = by_order.iter().map(|note| note.id.clone()).collect();
+            for (order, id) in ids.iter().enumerate() {
+                if let Some(note) = self.notes.get_mut(id) {
+                    note.order = order as i64;
+                }
+            }
+        }
+
+        let ids = self.ordered_ids();
+        let Some(position) = ids.iter().position(|other| other == id) else {
+            return;
+        };
+        let Some(neighbour) = position
+            .checked_add_signed(offset)
+            .and_then(|neighbour| ids.get(neighbour))
+        else {
+            return;
+        };
+
+        let order = self.notes[id].order;
+        let neighbour_order = self.notes[neighbour].order;
+        if let Some(note) = self.notes.get_mut(id) {
+            note.order = neighbour_order;
+        }
+        if let Some(note) = self.notes.get_mut(neighbour) {
+            note.order = order;
+        }
+        self.unsaved_changes = true;
+    }
+
+    /// Closes the window. Preferences such as window geometry are small and
+    /// never worth asking about, so any pending ones are written first.
+    fn quit(&mut self) -> Command<Message> {
+        // A background write may not finish before the process exits, so
+        // write the same notes again here, where quitting waits for it.
+        if self.saving && !self.unsaved_changes {
+            self.unsaved_changes = true;
+            if let Some(job) = self.save_job() {
+                let _ = job.run();
+            }
+        }
+        if self.unsaved_preferences {
+            self.unsaved_preferences = false;
+            // Best effort: the window is going away either way.
+            let _ = self.save_preferences();
+        }
+        if self.locked_by.is_none() {
+            let _ = storage::release_lock(&self.storage_path);
+        }
+        window::close(window::Id::MAIN)
+    }
+
+    fn set_font_size(&mut self, size: u16) {
+        self.font_size = size.clamp(MIN_FONT_SIZE, MAX_FONT_SIZE);
+        if let Err(e) = self.save_preferences() {
+            self.push_toast(Severity::Error, e.to_string());
+        }
+    }
+
+    /// Ids of the notes shown in the sidebar, in display order.
+    fn ordered_ids(&self) -> Vec<String> {
+        self.visible_notes()
+            .into_iter()
+            .map(|note| note.id.clone())
+            .collect()
+    }
+
+    /// Moves the selection `offset` places through the displayed list.
+    /// Selection clamps at either end rather than wrapping, so holding an
+    /// arrow key stops on the first or last note.
+    fn select_adjacent(&mut self, offset: isize) {
+        let ids = self.ordered_ids();
+        if ids.is_empty() {
+            return;
+        }
+        let position = self
+            .current_note
+            .as_ref()
+            .and_then(|current| ids.iter().position(|id| id == current));
+        let next = match position {
+            Some(position) => position.saturating_add_signed(offset).min(ids.len() - 1),
+            None if offset < 0 => ids.len() - 1,
+            None => 0,
+        };
+        if position != Some(next) {
+            self.open_note(Some(ids[next].clone()));
+        }
+    }
+
+    /// Puts `note` with the live notes or in the trash, as its
+    /// `trashed_at` says.
+    fn file_note(&mut self, note: Note) {
+        if note.trashed_at.is_some() {
+            self.trash.insert(note.id.clone(), note);
+        } else {
+            self.notes.insert(note.id.clone(), note);
+        }
+    }
+
+    /// Moves `id` to the trash, offering to undo it from a toast.
+    fn request_delete(&mut self, id: String) {
+        let Some(title) = self.notes.get(&id).map(|note| note.title.clone()) else {
+            return;
+        };
+        let deleted = DeletedNote {
+            tab: self.open_tabs.iter().position(|tab| *tab == id),
+            was_current: self.current_note.as_ref() == Some(&id),
+        };
+        self.delete_note(&id);
+        self.recently_deleted.insert(id.clone(), deleted);
+        let message = format!("Moved '{}' to Trash, where it's kept until emptied", title);
+        self.push_toast(Severity::Info, message);
+        if let Some(toast) = self.toasts.last_mut() {
+            toast.action = Some(("Undo", Message::UndoDelete(id)));
+        }
+    }
+
+    fn delete_note(&mut self, id: &str) {
+        if let Some(mut note) = self.notes.remove(id) {
+            note.trashed_at = Some(Utc::now());
+            self.trash.insert(note.id.clone(), note);
+            self.unsaved_changes = true;
+        }
+        self.parked_editors.remove(id);
+        self.close_tab(id);
+    }
+
+    /// Closes the tab for `id`, switching to its neighbour if it was the
+    /// active one.
+    fn close_tab(&mut self, id: &str) {
+        let Some(index) = self.open_tabs.iter().position(|tab| tab == id) else {
+            return;
+        };
+        self.open_tabs.remove(index);
+        self.unsaved_preferences = true;
+        if self.current_note.as_deref() == Some(id) {
+            let next = self
+                .open_tabs
+                .get(index)
+                .or_else(|| self.open_tabs.last())
+                .cloned();
+            self.open_note(next);
+        }
+    }
+
+    /// Swaps in new content for the current note from outside the editor,
+    /// as one undoable edit.
+    fn replace_current_content(&mut self, content: String) {
+        self.editor = text_editor::Content::with_text(&content);
+        // A large note's preview is a snapshot; keep it in step.
+        if let Some((id, snapshot)) = &mut self.preview_snapshot {
+            if self.current_note.as_deref() == Some(id.as_str()) {
+                *snapshot = content.clone();
+            }
+        }
+        self.edit_current_note(|note| note.content = content);
+    }
+
+    /// Applies `edit` to the current note and marks it as modified. A
+    /// content or title change records an undo entry first and counts as
+    /// one revision per entry, so a burst of typing is one.
+    fn edit_current_note(&mut self, edit: impl FnOnce(&mut Note)) {
+        let Some(id) = self.current_note.clone() else {
+            return;
+        };
+        let Some(note) = self.notes.get_mut(&id) else {
+            return;
+        };
+
+        let now = Instant::now();
+        let before = NotesSnapshot::of(note, now);
+        edit(note);
+        note.updated_at = Utc::now();
+        self.unsaved_changes = true;
+        self.last_edit = now;
+        if note.content == before.content && note.title == before.title {
+            return;
+        }
+
+        match self.undo_stack.last_mut() {
+            Some(snapshot)
+                if snapshot.id == id
+                    && now.duration_since(snapshot.last_edit) < UNDO_COALESCE_WINDOW =>
+            {
+                snapshot.last_edit = now;
+            }
+            _ => {
+                note.revision += 1;
+                self.undo_stack.push(before);
+            }
+        }
+        self.redo_stack.clear();
+    }
+
+    /// Puts the snapshotted text back and returns the text it replaced, so
+    /// the caller can push it onto the opposite stack.
+    fn restore_snapshot(&mut self, snapshot: NotesSnapshot) -> Option<NotesSnapshot> {
+        let current = self.notes.get_mut(&snapshot.id)?;
+        let replaced = NotesSnapshot::of(current, snapshot.last_edit);
+        current.title = snapshot.title;
+        current.title_is_manual = snapshot.title_is_manual;
+        current.content = snapshot.content;
+        current.updated_at = snapshot.updated_at;
+        // Undoing is a change too; the count only ever goes up.
+        if current.content != replaced.content || current.title != replaced.title {
+            current.revision = replaced.revision + 1;
+        }
+        if self.current_note.as_ref() == Some(&replaced.id) {
+            self.editor = text_editor::Content::with_text(&current.content);
+        }
+        self.unsaved_changes = true;
+        Some(replaced)
+    }
+
+    /// Trims the title of note `id` now that editing it is over, replacing
+    /// a blank one. Typing goes through untouched so spaces between words
+    /// aren't lost mid-word.
+    fn commit_title(&mut self, id: &str) {
+        let Some(note) = self.notes.get_mut(id) else {
+            return;
+        };
+        let content = if note.encrypted {
+            ""
+        } else {
+            note.content.as_str()
+        };
+        let title = normalize_title(&note.title, content);
+        if title != note.title {
+            // A blank title hands naming back to the content.
+            note.title_is_manual = !note.title.trim().is_empty();
+            note.title = title;
+            note.updated_at = Utc::now();
+            self.unsaved_changes = true;
+        }
+        let new = note.title.clone();
+        if let Some((_, old)) = self
+            .retitled
+            .take_if(|(retitled, _)| retitled.as_str() == id)
+        {
+            self.offer_link_update(old, new);
+        }
+    }
+
+    /// After a note was retitled from `old` to `new`, offers to point the
+    /// links to `old` at `new`, if any note has them.
+    fn offer_link_update(&mut self, old: String, new: String) {
+        if old.trim().eq_ignore_ascii_case(new.trim()) {
+            return;
+        }
+        let linking = self.link_index.backlinks(&old).count();
+        if linking == 0 {
+            return;
+        }
+        let message = format!("Links to '{}' found in {}", old, count_notes(linking));
+        self.push_toast(Severity::Info, message);
+        if let Some(toast) = self.toasts.last_mut() {
+            toast.action = Some(("Update Links", Message::RetargetLinks(old, new)));
+        }
+    }
+
+    /// Makes `id` the current note and loads its content into the editor.
+    fn open_note(&mut self, id: Option<String>) {
+        if let Some(previous) = self.current_note.clone() {
+            if Some(&previous) != id.as_ref() {
+                self.commit_title(&previous);
+            }
+        }
+        let previous = std::mem::replace(&mut self.editor, text_editor::Content::new());
+        if self.current_note != id {
+            if let Some(current) = &self.current_note {
+                self.parked_editors.insert(current.clone(), previous);
+            }
+            self.unsaved_preferences = true;
+        }
+        if let Some(note) = id.as_ref().and_then(|id| self.notes.get(id)) {
+            if !note.encrypted {
+                // A parked editor is stale if the note changed since, e.g.
+                // through undo or a ticked checkbox in the preview.
+                self.editor = match self.parked_editors.remove(&note.id) {
+                    Some(editor) if editor_text(&editor) == note.content => editor,
+                    _ => text_editor::Content::with_text(&note.content),
+                };
+            }
+        }
+        if let Some(id) = &id {
+            if !self.open_tabs.contains(id) {
+                self.open_tabs.push(id.clone());
+                self.unsaved_preferences = true;
+            }
+        }
+        if let Some(bar) = &mut self.find_bar {
+            bar.next = 0;
+        }
+        self.due_input = id
+            .as_ref()
+            .and_then(|id| self.notes.get(id))
+            .and_then(|note| note.due)
+            .map(format_due)
+            .unwrap_or_default();
+        self.current_note = id;
+    }
+
+    /// Encrypts a note's content with `key` and drops any undo history
+    /// that would still hold its plaintext.
+    fn encrypt_note(
+        &mut self,
+        id: &str,
+        key: &crypto::SealingKey,
+    ) -> Result<(), crypto::CryptoError> {
+        let Some(note) = self.notes.get_mut(id) else {
+            return Ok(());
+        };
+        note.content = key.seal(&note.content)?;
+        note.encrypted = true;
+        self.unsaved_changes = true;
+        self.parked_editors.remove(id);
+        self.undo_stack.retain(|snapshot| snapshot.id != id);
+        self.redo_stack.retain(|snapshot| snapshot.id != id);
+        if self.current_note.as_deref() == Some(id) {
+            self.open_note(Some(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Writes just the current note into the notes file on disk, keeping
+    /// the file's copy of every other note and leaving their edits pending.
+    fn save_current_note(&mut self) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let Some(note) = self.current_note.as_ref().and_then(|id| self.notes.get(id)) else {
+            return Ok(None);
+        };
+        if let Some(pid) = self.locked_by {
+            return Err(format!(
+                "Not saved: another window (process {}) has these notes open",
+                pid
+            )
+            .into());
+        }
+        let title = note.title.clone();
+        let mut note = note.clone();
+        if let Some(unlocked) = self.unlocked.get(&note.id) {
+            note.content = unlocked.seal(&note.content)?;
+            note.encrypted = true;
+        }
+        // Wait out any background save rather than race its rename.
+        let mut written = self.written.lock().unwrap_or_else(PoisonError::into_inner);
+        written.check(&self.storage_path)?;
+        storage::write_note_into_file(&self.storage_path, note)?;
+        written.fingerprint = storage::fingerprint(&self.storage_path);
+        Ok(Some(title))
+    }
+
+    /// Copies the notes for a write of `storage_path`, or returns `None`
+    /// when there's nothing to write. Pending changes count as saved from
+    /// here on; `finish_save` marks them unsaved again if the write fails.
+    fn save_job(&mut self) -> Option<SaveJob> {
+        if !self.unsaved_changes
+            || self.storage_read_only
+            || self.disk_changed
+            || self.locked_by.is_some()
+        {
+            return None;
+        }
+        let previous = std::mem::take(&mut self.snapshot);
+        let notes: HashMap<String, Arc<Note>> = self
+            .notes
+            .values()
+            .chain(self.trash.values())
+            .map(|note| {
+                let shared = match previous.get(&note.id) {
+                    Some(shared) if **shared == *note => Arc::clone(shared),
+                    _ => Arc::new(note.clone()),
+                };
+                (note.id.clone(), shared)
+            })
+            .collect();
+        self.snapshot = notes.clone();
+        self.save_generation += 1;
+        self.unsaved_changes = false;
+        Some(SaveJob {
+            path: self.storage_path.clone(),
+            notes,
+            unlocked: self.unlocked.clone(),
+            generation: self.save_generation,
+            written: Arc::clone(&self.written),
+        })
+    }
+
+    /// Applies the outcome of a `SaveJob`, returning the error to show, if
+    /// any. A read-only notes file raises its banner instead.
+    fn finish_save(&mut self, result: Result<Option<String>, Arc<io::Error>>) -> Option<String> {
+        match result {
+            Ok(warning) => warning,
+            Err(e) => {
+                self.unsaved_changes = true;
+                if is_read_only(e.as_ref()) {
+                    self.storage_read_only = true;
+                    None
+                } else if e
+                    .get_ref()
+                    .is_some_and(|inner| inner.is::<storage::ChangedOnDisk>())
+                {
+                    self.disk_changed = true;
+                    self.conflict_prompt = true;
+                    None
+                } else {
+                    Some(e.to_string())
+                }
+            }
+        }
+    }
+
+    fn lock_written(&self) -> std::sync::MutexGuard<'_, Written> {
+        self.written.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Re-reads the notes file after it changed on disk and merges it with
+    /// the open notes: a note edited here more recently than on disk keeps
+    /// this app's version, and notes only one side has are kept.
+    fn reload_from_disk(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let fingerprint = storage::fingerprint(&self.storage_path);
+        let on_disk = storage::read_notes_file(&self.storage_path)?;
+        for (id, note) in on_disk {
+            let newer = self
+                .notes
+                .get(&id)
+                .or_else(|| self.trash.get(&id))
+                .is_none_or(|local| local.updated_at < note.updated_at);
+            if newer {
+                // The disk copy is sealed again if it's encrypted.
+                self.unlocked.remove(&id);
+                self.parked_editors.remove(&id);
+                self.notes.remove(&id);
+                self.trash.remove(&id);
+                self.file_note(note);
+            }
+        }
+        let trash = &self.trash;
+        self.open_tabs.retain(|id| !trash.contains_key(id));
+        self.lock_written().fingerprint = fingerprint;
+        self.disk_changed = false;
+        // Whatever was kept from here still needs writing.
+        self.unsaved_changes = true;
+        self.open_note(self.current_note.clone());
+        Ok(())
+    }
+
+    /// Starts writing pending notes on a blocking thread, unless a write is
+    /// already running; the next autosave tick picks up anything newer.
+    fn save_in_background(&mut self) -> Command<Message> {
+        if self.saving {
+            return Command::none();
+        }
+        match self.save_job() {
+            Some(job) => {
+                self.saving = true;
+                Command::perform(job.run_in_background(), Message::SaveComplete)
+            }
+            None => Command::none(),
+        }
+    }
+
+    /// Writes any pending notes and preferences to disk straight away.
+    fn save_now(&mut self) {
+        if let Some(e) = self.save_job().and_then(|job| self.finish_save(job.run())) {
+            self.push_toast(Severity::Error, e);
+        }
+        self.flush_preferences();
+    }
+
+    fn flush_preferences(&mut self) {
+        if self.unsaved_preferences {
+            self.unsaved_preferences = false;
+            if let Err(e) = self.save_preferences() {
+                self.push_toast(Severity::Error, e.to_string());
+            }
+        }
+    }
+
+    fn push_toast(&mut self, severity: Severity, text: impl Into<String>) {
+        self.toasts.push(Toast {
+            text: text.into(),
+            severity,
+            spawned: Instant::now(),
+            action: None,
+        });
+        self.unscheduled_toasts += 1;
+    }
+
+    /// Deletes the pre-import backup once its "Undo Import" toast is gone.
+    fn discard_stale_import_backup(&mut self) {
+        let offered = self
+            .toasts
+            .iter()
+            .any(|toast| matches!(toast.action, Some((_, Message::UndoImport))));
+        if !offered {
+            if let Some(backup) = self.import_backup.take() {
+                let _ = fs::remove_file(backup);
+            }
+        }
+    }
+
+    /// Stops offering to put deleted notes back where they were open once
+    /// their "Undo" toast is gone. The notes stay in the trash, saved with
+    /// the others, until it's emptied.
+    fn forget_expired_deletes(&mut self) {
+        let toasts = &self.toasts;
+        self.recently_deleted.retain(|id, _| {
+            toasts.iter().any(|toast| {
+                matches!(&toast.action, Some((_, Message::UndoDelete(other))) if other == id)
+            })
+        });
+    }
+
+    fn save_preferences(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let preferences = Preferences {
+            sort_mode: self.sort_mode,
+            dark_mode: self.dark_mode,
+            reuse_blank_notes: self.reuse_blank_notes,
+            show_snippets: self.show_snippets,
+            sidebar_style: self.sidebar_style,
+            autosave_interval_secs: self.autosave_interval_secs,
+            similarity_threshold: self.similarity_threshold,
+            last_seen_version: self.last_seen_version.clone(),
+            split_ratio: self.split_ratio,
+            window: self.window,
+            font_size: self.font_size,
+            current_note: self.current_note.clone(),
+            open_tabs: self.open_tabs.clone(),
+        };
+        storage::save_preferences(&preferences)
+    }
+}
+
+/// Whether a failed save was refused by the filesystem, so retrying the
+/// same path is pointless.
+fn is_read_only(error: &(dyn std::error::Error + 'static)) -> bool {
+    error.downcast_ref::<io::Error>().is_some_and(|e| {
+        matches!(
+            e.kind(),
+            io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem
+        )
+    })
+}
+
+/// While the divider is held, follows the cursor until the button is let go.
+fn handle_divider_drag(event: iced::Event, _status: event::Status) -> Option<Message> {
+    match event {
+        iced::Event::Mouse(mouse::Event::CursorMoved { position }) => {
+            Some(Message::DragDivider(position.x))
+        }
+        iced::Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+            Some(Message::ReleaseDivider)
+        }
+        _ => None,
+    }
+}
+
+/// "1 note" or "N notes".
+pub fn count_notes(count: usize) -> String {
+    match count {
+        1 => "1 note".to_string(),
+        count => format!("{} notes", count),
+    }
+}
+
+/// `path` made absolute, so messages say exactly where a file went.
+fn resolved(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Starts one dismissal timer for each of `count` newly pushed toasts.
+fn expire_toasts(count: usize) -> Command<Message> {
+    Command::batch((0..count).map(|_| {
+        Command::perform(tokio::time::sleep(TOAST_DURATION), |_| {
+            Message::ExpireToasts
+        })
+    }))
+}
+
+fn search_input_id() -> text_input::Id {
+    text_input::Id::new("search")
+}
+
+fn rename_input_id() -> text_input::Id {
+    text_input::Id::new("rename")
+}
+
+fn title_input_id() -> text_input::Id {
+    text_input::Id::new("title")
+}
+
+/// Focuses the editor's title field with its text selected, so typing
+/// replaces "New Note".
+fn focus_title() -> Command<Message> {
+    Command::batch([
+        text_input::focus(title_input_id()),
+        text_input::select_all(title_input_id()),
+    ])
+}
+
+/// Case-insensitive substring test. ASCII queries are compared byte-wise
+/// without allocating, which keeps filtering cheap for long note bodies.
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    if needle.is_empty() {
+        return true;
+    }
+    if needle.is_ascii() {
+        let needle = needle.as_bytes();
+        haystack
+            .as_bytes()
+            .windows(needle.len())
+            .any(|window| window.eq_ignore_ascii_case(needle))
+    } else {
+        haystack.to_lowercase().contains(&needle.to_lowercase())
+    }
+}
+
+fn view_snippet<'a>(content: &str, query: &str) -> Element<'a, Message> {
+    let grey = Color::from_rgb(0.5, 0.5, 0.5);
+    let line = snippet(content, query);
+    let Some(highlight) = line.highlight else {
+        return text(line.text).size(12).style(grey).into();
+    };
+    // iced 0.12's text has no styled spans, so the match is its own widget.
+    row![
+        text(&line.text[..highlight.start]).size(12).style(grey),
+        text(&line.text[highlight.clone()])
+            .size(12)
+            .style(Color::from_rgb(0.9, 0.5, 0.0)),
+        text(&line.text[highlight.end..]).size(12).style(grey),
+    ]
+    .into()
+}
+
+fn view_color_sliders<'a>([r, g, b]: [f32; 3]) -> Element<'a, Message> {
+    column![
+        color_channel("R", r, move |r| Message::SetCustomColor(r, g, b)),
+        color_channel("G", g, move |g| Message::SetCustomColor(r, g, b)),
+        color_channel("B", b, move |b| Message::SetCustomColor(r, g, b)),
+    ]
+    .spacing(5)
+    .into()
+}
+
+fn color_channel<'a>(
+    label: &'a str,
+    value: f32,
+    on_change: impl Fn(f32) -> Message + 'a,
+) -> Element<'a, Message> {
+    row![
+        text(label).size(12).width(15),
+        slider(0.0..=1.0, value, on_change).step(0.01),
+    ]
+    .spacing(5)
+    .into()
+}
+
+fn note_label(note: &Note) -> String {
+    let mut label = String::new();
+    if note.pinned {
+        label.push_str("📌 ");
+    }
+    if note.encrypted || note.locked {
+        label.push_str("🔒 ");
+    }
+    label.push_str(&note.title);
+    label
+}
+
+/// The font a note's content is edited and previewed in.
+fn note_font(note: &Note) -> Font {
+    if note.monospace {
+        Font::MONOSPACE
+    } else {
+        Font::DEFAULT
+    }
+}
+
+/// `Content::text` always appends a trailing newline; drop it so that
+/// round-tripping a note through the editor leaves its content unchanged.
+fn editor_text(editor: &text_editor::Content) -> String {
+    let mut text = editor.text();
+    if text.ends_with('\n') {
+        text.pop();
+    }
+    text
+}
+
+/// Maps global keyboard shortcuts to messages. Command-modified shortcuts
+/// fire even while a text field has focus; plain keys would be typing or
+/// cursor movement there, so they only apply when no widget captured them.
+fn handle_event(event: iced::Event, status: event::Status) -> Option<Message> {
+    let (key, modifiers) = match event {
+        iced::Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => {
+            (key, modifiers)
+        }
+        iced::Event::Window(_, window::Event::Resized { width, height }) => {
+            return Some(Message::WindowResized(width, height));
+        }
+        iced::Event::Window(_, window::Event::Moved { x, y }) => {
+            return Some(Message::WindowMoved(x, y));
+        }
+        iced::Event::Window(_, window::Event::Unfocused) => {
+            return Some(Message::WindowUnfocused);
+        }
+        iced::Event::Window(_, window::Event::CloseRequested) => {
+            return Some(Message::CloseRequested);
+        }
+        iced::Event::Window(_, window::Event::FileHovered(_)) => {
+            return Some(Message::FilesHovered);
+        }
+        iced::Event::Window(_, window::Event::FilesHoveredLeft) => {
+            return Some(Message::FilesHoveredLeft);
+        }
+        iced::Event::Window(_, window::Event::FileDropped(path)) => {
+            return Some(Message::FileDropped(path));
+        }
+        _ => return None,
+    };
+
+    if modifiers.command() {
+        return match key.as_ref() {
+            keyboard::Key::Character(c) => menu::shortcut_message(c, modifiers.shift()),
+            _ => None,
+        };
+    }
+
+    // Text inputs capture Escape to drop focus, but it should still cancel
+    // an in-place rename or clear the search box.
+    if key == keyboard::Key::Named(keyboard::key::Named::Escape) {
+        return Some(Message::Escape);
+    }
+
+    if status == event::Status::Captured {
+        return None;
+    }
+
+    match key.as_ref() {
+        keyboard::Key::Named(keyboard::key::Named::ArrowUp) => Some(Message::SelectPrevious),
+        keyboard::Key::Named(keyboard::key::Named::ArrowDown) => Some(Message::SelectNext),
+        keyboard::Key::Named(keyboard::key::Named::Delete | keyboard::key::Named::Backspace) => {
+            Some(Message::DeleteSelected)
+        }
+        _ => None,
+    }
+}
+
+/// Parses a due date typed in local time, as `YYYY-MM-DD HH:MM` or just
+/// `YYYY-MM-DD` (meaning the end of that day).
+fn parse_due(input: &str) -> Option<DateTime<Utc>> {
+    let input = input.trim();
+    let naive = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(23, 59, 0)
+        })?;
+    Some(
+        Local
+            .from_local_datetime(&naive)
+            .earliest()?
+            .with_timezone(&Utc),
+    )
+}
+
+fn format_due(due: DateTime<Utc>) -> String {
+    due.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+/// Describes how long before `now` the moment `then` was, e.g. "3 minutes
+/// ago". Anything older than yesterday is shown as a date.
+fn humanize_age(then: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let elapsed = now.signed_duration_since(then);
+    let plural = |count: i64, unit: &str| {
+        format!(
+            "{} {}{} ago",
+            count,
+            unit,
+            if count == 1 { "" } else { "s" }
+        )
+    };
+    if elapsed.num_minutes() < 1 {
+        "just now".to_string()
+    } else if elapsed.num_hours() < 1 {
+        plural(elapsed.num_minutes(), "minute")
+    } else if elapsed.num_days() < 1 {
+        plural(elapsed.num_hours(), "hour")
+    } else if elapsed.num_days() < 2 {
+        "yesterday".to_string()
+    } else {
+        then.format("%b %-d, %Y").to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ago(now: DateTime<Utc>, elapsed: chrono::Duration) -> String {
+        humanize_age(now - elapsed, now)
+    }
+
+    #[test]
+    fn ages_fall_into_buckets_at_their_boundaries() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 15, 12, 0, 0).unwrap();
+        let seconds = chrono::Duration::seconds;
+        let minutes = chrono::Duration::minutes;
+        let hours = chrono::Duration::hours;
+        let days = chrono::Duration::days;
+
+        assert_eq!(ago(now, seconds(0)), "just now");
+        assert_eq!(ago(now, seconds(59)), "just now");
+        assert_eq!(ago(now, seconds(60)), "1 minute ago");
+        assert_eq!(ago(now, minutes(3)), "3 minutes ago");
+        assert_eq!(ago(now, minutes(59) + seconds(59)), "59 minutes ago");
+        assert_eq!(ago(now, hours(1)), "1 hour ago");
+        assert_eq!(ago(now, hours(2)), "2 hours ago");
+        assert_eq!(ago(now, hours(23) + minutes(59)), "23 hours ago");
+        assert_eq!(ago(now, days(1)), "yesterday");
+        assert_eq!(ago(now, days(2) - seconds(1)), "yesterday");
+        assert_eq!(ago(now, days(2)), "Mar 13, 2024");
+        assert_eq!(ago(now, days(400)), "Feb 9, 2023");
+    }
+
+    #[test]
+    fn a_time_in_the_future_is_just_now() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 15, 12, 0, 0).unwrap();
+        assert_eq!(ago(now, chrono::Duration::minutes(-5)), "just now");
+    }
+
+    #[test]
+    fn a_replacing_import_is_left_to_be_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut app, _) = NotesApp::new(Some(dir.path().join(NOTES_FILE)));
+        let imported = Note::new("Imported", "from elsewhere".to_string());
+        let path = dir.path().join("import.json");
+        storage::write_notes_file(&path, HashMap::from([(imported.id.clone(), imported)])).unwrap();
+        assert!(!app.unsaved_changes);
+
+        app.import_notes(&path).unwrap();
+        assert_eq!(app.notes.len(), 1);
+        assert!(app.unsaved_changes);
+    }
+}
@@ -0,0 +1,382 @@
+//! Importing notes from files and exporting them, whole or in part.
+
+use iced::Command;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::{count_notes, resolved, seal_unlocked, NotesApp};
+use crate::message::Message;
+use crate::note::{note_to_markdown, Note};
+use crate::storage::{self, NOTES_FILE};
+use crate::toast::Severity;
+use crate::{crypto, html, trash};
+
+impl NotesApp {
+    /// Handles the messages that read notes in from files or write them
+    /// out to them.
+    pub(super) fn handle_transfer(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::ImportNotes => {
+                let file = rfd::FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .pick_file();
+                if file.is_some() {
+                    self.pending_import = file;
+                }
+            }
+            Message::ImportReplace => {
+                if let Some(path) = self.pending_import.take() {
+                    let backup = storage::import_backup_path();
+                    if let Err(e) = self
+                        .notes_for_disk()
+                        .map_err(Into::into)
+                        .and_then(|notes| storage::write_notes_file(&backup, notes))
+                    {
+                        let message = format!("Import cancelled, could not back up notes: {}", e);
+                        self.push_toast(Severity::Error, message);
+                        return Command::none();
+                    }
+                    match self.import_notes(&path) {
+                        Ok(()) => {
+                            let message = format!(
+                                "Loaded {} from {}",
+                                count_notes(self.notes.len()),
+                                resolved(&path).display()
+                            );
+                            self.push_toast(Severity::Success, message);
+                            if let Some(toast) = self.toasts.last_mut() {
+                                toast.action = Some(("Undo Import", Message::UndoImport));
+                            }
+                            self.import_backup = Some(backup);
+                        }
+                        Err(e) => {
+                            let _ = fs::remove_file(&backup);
+                            self.report_import(&path, Err(e));
+                        }
+                    }
+                }
+            }
+            Message::UndoImport => {
+                if let Some(backup) = self.import_backup.take() {
+                    self.toasts
+                        .retain(|toast| !matches!(toast.action, Some((_, Message::UndoImport))));
+                    match self.import_notes(&backup) {
+                        Ok(()) => {
+                            let _ = fs::remove_file(&backup);
+                            self.unsaved_changes = true;
+                            self.push_toast(Severity::Success, "Import undone");
+                        }
+                        Err(e) => {
+                            let message = format!(
+                                "Could not undo import, the old notes are in {}: {}",
+                                backup.display(),
+                                e
+                            );
+                            self.push_toast(Severity::Error, message);
+                        }
+                    }
+                }
+            }
+            Message::ImportMerge => {
+                if let Some(path) = self.pending_import.take() {
+                    let result = self.merge_notes(&path).map(|merged| {
+                        format!(
+                            "Merged {} from {}",
+                            count_notes(merged),
+                            resolved(&path).display()
+                        )
+                    });
+                    self.report_import(&path, result);
+                }
+            }
+            Message::CancelImport => {
+                self.pending_import = None;
+            }
+            Message::FilesHovered => self.drop_hover = true,
+            Message::FilesHoveredLeft => self.drop_hover = false,
+            Message::FileDropped(path) => {
+                self.drop_hover = false;
+                let is_json = path
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+                if is_json {
+                    self.pending_import = Some(path);
+                } else if storage::is_text_file(&path) {
+                    match storage::read_text_note(&path) {
+                        Ok(mut note) => {
+                            note.notebook = self.selected_notebook.clone();
+                            note.order = self.next_order();
+                            let id = note.id.clone();
+                            let message = format!("Imported '{}'", note.title);
+                            self.notes.insert(id.clone(), note);
+                            self.unsaved_changes = true;
+                            self.open_note(Some(id));
+                            self.push_toast(Severity::Success, message);
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                            let message = format!("{} isn't valid UTF-8 text", path.display());
+                            self.push_toast(Severity::Error, message);
+                        }
+                        Err(e) => {
+                            let message = format!("Could not import {}: {}", path.display(), e);
+                            self.push_toast(Severity::Error, message);
+                        }
+                    }
+                } else {
+                    let message = format!(
+                        "Can't import {}: drop a .json notes file or a .md or .txt file",
+                        path.display()
+                    );
+                    self.push_toast(Severity::Error, message);
+                }
+            }
+            Message::ImportDirectory => {
+                if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                    match self.import_directory(&dir) {
+                        Ok((imported, skipped)) => {
+                            let message = format!(
+                                "Imported {}, skipped {} files that weren't valid UTF-8",
+                                count_notes(imported),
+                                skipped
+                            );
+                            self.push_toast(Severity::Success, message);
+                        }
+                        Err(e) => self.push_toast(Severity::Error, e.to_string()),
+                    }
+                }
+            }
+            Message::ExportNotes => {
+                let file = rfd::FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .set_file_name(NOTES_FILE)
+                    .save_file();
+                if let Some(path) = file {
+                    match self.export_notes(&path) {
+                        Ok(_) => {
+                            let message = format!(
+                                "Saved {} to {}",
+                                count_notes(self.notes.len()),
+                                resolved(&path).display()
+                            );
+                            self.push_toast(Severity::Success, message);
+                        }
+                        Err(e) => self.push_toast(Severity::Error, e.to_string()),
+                    }
+                }
+            }
+            Message::ExportVisible => {
+                let file = rfd::FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .set_file_name(NOTES_FILE)
+                    .save_file();
+                if let Some(path) = file {
+                    let visible: HashSet<String> = self.ordered_ids().into_iter().collect();
+                    let written =
+                        self.notes_for_disk()
+                            .map_err(Into::into)
+                            .and_then(|mut notes| {
+                                notes.retain(|id, _| visible.contains(id));
+                                storage::write_notes_file(&path, notes)
+                            });
+                    match written {
+                        Ok(()) => {
+                            let message = format!(
+                                "Exported {} of {} to {}",
+                                visible.len(),
+                                count_notes(self.notes.len()),
+                                resolved(&path).display()
+                            );
+                            self.push_toast(Severity::Success, message);
+                        }
+                        Err(e) => self.push_toast(Severity::Error, e.to_string()),
+                    }
+                }
+            }
+            Message::ExportCurrentMarkdown => {
+                let Some(note) = self.current_note.as_ref().and_then(|id| self.notes.get(id))
+                else {
+                    self.push_toast(Severity::Info, "No note selected");
+                    return Command::none();
+                };
+                if note.encrypted {
+                    self.push_toast(Severity::Info, "Unlock the note before exporting it");
+                    return Command::none();
+                }
+                let file = rfd::FileDialog::new()
+                    .add_filter("Markdown", &["md"])
+                    .set_file_name(format!("{}.md", note.title))
+                    .save_file();
+                if let Some(path) = file {
+                    match fs::write(&path, note_to_markdown(note)) {
+                        Ok(_) => {
+                            let message = format!(
+                                "Exported '{}' to {}",
+                                note.title,
+                                resolved(&path).display()
+                            );
+                            self.push_toast(Severity::Success, message);
+                        }
+                        Err(e) => self.push_toast(Severity::Error, e.to_string()),
+                    }
+                }
+            }
+            Message::ExportArchive => {
+                let file = rfd::FileDialog::new()
+                    .add_filter("Zip archive", &["zip"])
+                    .set_file_name("notes.zip")
+                    .save_file();
+                if let Some(path) = file {
+                    let written = self
+                        .notes_for_disk()
+                        .map_err(Into::into)
+                        .and_then(|notes| storage::notes_archive(&notes))
+                        .and_then(|archive| Ok(fs::write(&path, archive)?));
+                    match written {
+                        Ok(()) => {
+                            let message = format!(
+                                "Exported {} to {}",
+                                count_notes(self.notes.len()),
+                                resolved(&path).display()
+                            );
+                            self.push_toast(Severity::Success, message);
+                        }
+                        Err(e) => self.push_toast(Severity::Error, e.to_string()),
+                    }
+                }
+            }
+            Message::ExportHtml => {
+                let file = rfd::FileDialog::new()
+                    .add_filter("HTML", &["html"])
+                    .set_file_name("notes.html")
+                    .save_file();
+                if let Some(path) = file {
+                    match fs::write(&path, html::render(&self.sorted_notes())) {
+                        Ok(_) => {
+                            let message = format!(
+                                "Exported {} to {}",
+                                count_notes(self.notes.len()),
+                                resolved(&path).display()
+                            );
+                            self.push_toast(Severity::Success, message);
+                        }
+                        Err(e) => self.push_toast(Severity::Error, e.to_string()),
+                    }
+                }
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+
+    /// Reads a notes file for import, repairing invalid ids and saying
+    /// how many there were.
+    fn read_import(
+        &mut self,
+        path: &Path,
+    ) -> Result<HashMap<String, Note>, Box<dyn std::error::Error>> {
+        let (notes, repaired) = storage::read_notes_file_repairing_ids(path)?;
+        if repaired > 0 {
+            let message = format!(
+                "Gave {} in {} a new id, as theirs were missing or duplicated",
+                count_notes(repaired),
+                path.display()
+            );
+            self.push_toast(Severity::Info, message);
+        }
+        Ok(notes)
+    }
+
+    pub(super) fn import_notes(&mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        (self.notes, self.trash) = trash::split(self.read_import(path)?);
+        self.unsaved_changes = true;
+        // Every note read from a file is sealed again, so no session
+        // password applies any more.
+        self.unlocked.clear();
+        self.parked_editors.clear();
+        let notes = &self.notes;
+        self.open_tabs.retain(|id| notes.contains_key(id));
+        if !self
+            .current_note
+            .as_ref()
+            .is_some_and(|id| notes.contains_key(id))
+        {
+            self.open_note(self.open_tabs.last().cloned());
+        }
+        Ok(())
+    }
+
+    /// Adds the notes in the file at `path` to the open ones, returning how
+    /// many were added. An imported note whose id is already taken by a
+    /// different note gets a fresh id; exact copies are skipped.
+    fn merge_notes(&mut self, path: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+        let imported = self.read_import(path)?;
+        let mut merged = 0;
+        for (_, mut note) in imported {
+            match self
+                .notes
+                .get(&note.id)
+                .or_else(|| self.trash.get(&note.id))
+            {
+                Some(existing) if *existing == note => continue,
+                Some(_) => note.id = uuid::Uuid::new_v4().to_string(),
+                None => {}
+            }
+            self.file_note(note);
+            merged += 1;
+        }
+        if merged > 0 {
+            self.unsaved_changes = true;
+        }
+        Ok(merged)
+    }
+
+    /// Shows the outcome of an import as a toast, calling out files that
+    /// aren't notes files at all.
+    fn report_import(&mut self, path: &Path, result: Result<String, Box<dyn std::error::Error>>) {
+        match result {
+            Ok(message) => self.push_toast(Severity::Success, message),
+            Err(e) if e.is::<serde_json::Error>() => {
+                self.push_toast(
+                    Severity::Error,
+                    format!("{} isn't a valid notes file ({})", path.display(), e),
+                );
+            }
+            Err(e) => self.push_toast(Severity::Error, e.to_string()),
+        }
+    }
+
+    /// Creates a note from every `.txt`/`.md` file directly inside `dir`,
+    /// returning how many were imported and how many were skipped.
+    fn import_directory(&mut self, dir: &Path) -> io::Result<(usize, usize)> {
+        let (notes, skipped) = storage::read_text_notes(dir)?;
+        let imported = notes.len();
+        for note in notes {
+            self.notes.insert(note.id.clone(), note);
+        }
+        if imported > 0 {
+            self.unsaved_changes = true;
+        }
+        Ok((imported, skipped))
+    }
+
+    /// The notes as they should be written to disk: notes unlocked this
+    /// session are encrypted again.
+    fn notes_for_disk(&self) -> Result<HashMap<String, Note>, crypto::CryptoError> {
+        let mut notes = self.notes.clone();
+        notes.extend(
+            self.trash
+                .iter()
+                .map(|(id, note)| (id.clone(), note.clone())),
+        );
+        seal_unlocked(&mut notes, &self.unlocked)?;
+        Ok(notes)
+    }
+
+    /// Writes every note to `path`. Unlike saves, exports leave no
+    /// `backups/` folder beside the file they write.
+    fn export_notes(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        storage::write_notes_file(path, self.notes_for_disk()?)
+    }
+}
@@ -0,0 +1,73 @@
+//! Markdown task-list lines (`- [ ] item` / `- [x] item`) inside note content.
+
+/// A task-list line found in a note's content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item<'a> {
+    /// Zero-based line index within the content.
+    pub line: usize,
+    pub checked: bool,
+    pub label: &'a str,
+}
+
+fn parse_line(line: &str) -> Option<(bool, &str)> {
+    let rest = line.trim_start().strip_prefix("- [")?;
+    let (mark, label) = rest.split_at_checked(1)?;
+    let label = label.strip_prefix(']')?;
+    let label = label.strip_prefix(' ').unwrap_or(label);
+    match mark {
+        " " => Some((false, label)),
+        "x" | "X" => Some((true, label)),
+        _ => None,
+    }
+}
+
+/// Returns every task-list item in `content`, in order.
+pub fn items(content: &str) -> Vec<Item<'_>> {
+    content
+        .split('\n')
+        .enumerate()
+        .filter_map(|(line, text)| {
+            parse_line(text).map(|(checked, label)| Item {
+                line,
+                checked,
+                label,
+            })
+        })
+        .collect()
+}
+
+/// Flips the checkbox on line `line`, leaving every other byte of `content`
+/// untouched. Lines that aren't task items are returned unchanged.
+pub fn toggle(content: &str, line: usize) -> String {
+    content
+        .split('\n')
+        .enumerate()
+        .map(|(index, text)| match parse_line(text) {
+            Some((checked, _)) if index == line => {
+                // `parse_line` only accepts leading whitespace before the
+                // marker, so the first "- [" is the one to flip.
+                let mark = text.find("- [").unwrap_or_default() + 3;
+                let mut toggled = text.to_string();
+                toggled.replace_range(mark..mark + 1, if checked { " " } else { "x" });
+                toggled
+            }
+            _ => text.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Flips the task-list checkbox whose `[ ]`/`[x]` starts at byte `offset`,
+/// as the Markdown preview reports it, leaving every other byte untouched.
+/// `None` if there's no checkbox there, e.g. because the content changed
+/// since it was rendered.
+pub fn toggle_at(content: &str, offset: usize) -> Option<String> {
+    let flipped = match content.get(offset..offset + 3)? {
+        "[ ]" => "[x]",
+        "[x]" | "[X]" => "[ ]",
+        _ => return None,
+    };
+    let mut toggled = content.to_string();
+    toggled.replace_range(offset..offset + 3, flipped);
+    Some(toggled)
+}
@@ -0,0 +1,81 @@
+//! The `export` subcommand, which writes an export of the notes without
+//! opening a window, e.g. from a cron job.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::app::{count_notes, sorted_notes};
+use crate::{html, storage};
+
+const USAGE: &str = "usage: open_source_note_app_macos export --format <html|archive|json> \
+                     --out <FILE> [--notes <NOTES_FILE>]";
+
+/// What `export` writes. `archive` is the zip of Markdown files from
+/// File → Export Archive….
+enum Format {
+    Html,
+    Archive,
+    Json,
+}
+
+/// Runs `export` with the arguments that follow it and returns the exit
+/// code for the process.
+pub fn export(args: impl Iterator<Item = OsString>) -> i32 {
+    match run_export(args) {
+        Ok(message) => {
+            println!("{}", message);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+fn run_export(
+    mut args: impl Iterator<Item = OsString>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let (mut format, mut out, mut notes_path) = (None, None, None);
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or(USAGE)?;
+        match flag.to_str() {
+            Some("--format") => {
+                format = Some(match value.to_str() {
+                    Some("html") => Format::Html,
+                    Some("archive") => Format::Archive,
+                    Some("json") => Format::Json,
+                    _ => return Err(USAGE.into()),
+                })
+            }
+            Some("--out") => out = Some(PathBuf::from(value)),
+            Some("--notes") => notes_path = Some(PathBuf::from(value)),
+            _ => return Err(USAGE.into()),
+        }
+    }
+    let (Some(format), Some(out)) = (format, out) else {
+        return Err(USAGE.into());
+    };
+
+    // Read rather than load: a damaged file is reported, not quarantined.
+    let notes_path = notes_path.unwrap_or_else(storage::storage_path);
+    let mut notes = storage::read_notes_file(&notes_path)
+        .map_err(|e| format!("could not read {}: {}", notes_path.display(), e))?;
+    notes.retain(|_, note| note.trashed_at.is_none());
+    let count = notes.len();
+    match format {
+        // Same order as the sidebar at launch.
+        Format::Html => {
+            let sort_mode = storage::load_preferences().sort_mode;
+            fs::write(&out, html::render(&sorted_notes(&notes, sort_mode, false)))?
+        }
+        Format::Archive => fs::write(&out, storage::notes_archive(&notes)?)?,
+        Format::Json => storage::write_notes_file(&out, notes)?,
+    }
+    Ok(format!(
+        "Exported {} to {}",
+        count_notes(count),
+        out.display()
+    ))
+}
@@ -0,0 +1,103 @@
+//! Password-based encryption for locked notes.
+//!
+//! Content is sealed with AES-256-GCM under a key derived from the password
+//! with Argon2. The salt and nonce are stored alongside the ciphertext as a
+//! single base64 string, so a locked note stays a plain JSON string field.
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::fmt;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug)]
+pub enum CryptoError {
+    /// The stored ciphertext couldn't be decoded.
+    Malformed,
+    /// Decryption failed, which for AEAD almost always means a wrong password.
+    WrongPassword,
+    KeyDerivation(argon2::Error),
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoError::Malformed => write!(f, "the encrypted note data is damaged"),
+            CryptoError::WrongPassword => write!(f, "wrong password"),
+            CryptoError::KeyDerivation(e) => write!(f, "could not derive key: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+fn derive_key(password: &str, salt: &[u8]) -> Result<Key<Aes256Gcm>, CryptoError> {
+    let mut key = Key::<Aes256Gcm>::default();
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(CryptoError::KeyDerivation)?;
+    Ok(key)
+}
+
+/// A key derived from a password and salt. Holding on to it lets a note be
+/// sealed again without repeating the slow Argon2 derivation; every seal
+/// still gets a fresh nonce.
+pub struct SealingKey {
+    salt: [u8; SALT_LEN],
+    cipher: Aes256Gcm,
+}
+
+impl SealingKey {
+    /// Derives a key from `password` under a fresh random salt.
+    pub fn new(password: &str) -> Result<Self, CryptoError> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::with_salt(password, salt)
+    }
+
+    fn with_salt(password: &str, salt: [u8; SALT_LEN]) -> Result<Self, CryptoError> {
+        let cipher = Aes256Gcm::new(&derive_key(password, &salt)?);
+        Ok(Self { salt, cipher })
+    }
+
+    /// Encrypts `plaintext`, returning the encoded sealed text.
+    pub fn seal(&self, plaintext: &str) -> Result<String, CryptoError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| CryptoError::Malformed)?;
+
+        let mut sealed = Vec::with_capacity(SALT_LEN + NONCE_LEN + ciphertext.len());
+        sealed.extend_from_slice(&self.salt);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(BASE64.encode(sealed))
+    }
+}
+
+/// Reverses [`SealingKey::seal`] for a key derived from `password`, also
+/// handing back the key it derived so the content can be sealed again
+/// cheaply.
+pub fn decrypt(sealed: &str, password: &str) -> Result<(String, SealingKey), CryptoError> {
+    let sealed = BASE64.decode(sealed).map_err(|_| CryptoError::Malformed)?;
+    if sealed.len() < SALT_LEN + NONCE_LEN {
+        return Err(CryptoError::Malformed);
+    }
+    let (salt, rest) = sealed.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let salt = salt.try_into().map_err(|_| CryptoError::Malformed)?;
+    let key = SealingKey::with_salt(password, salt)?;
+    let plaintext = key
+        .cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| CryptoError::WrongPassword)?;
+    let plaintext = String::from_utf8(plaintext).map_err(|_| CryptoError::Malformed)?;
+    Ok((plaintext, key))
+}
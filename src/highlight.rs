@@ -0,0 +1,120 @@
+//! Syntax highlighting for fenced code blocks in the Markdown preview.
+//!
+//! Highlighting with syntect is far too slow to redo on every frame, so
+//! finished blocks are cached by a hash of their language and source. The
+//! cache keeps only the most recently drawn blocks, as every edit to a
+//! block being typed makes a new entry.
+
+use iced::Color;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, OnceLock};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+const THEME: &str = "InspiredGitHub";
+
+/// How many highlighted blocks the cache holds before dropping the least
+/// recently used.
+const MAX_CACHED: usize = 64;
+
+/// A run of source text drawn in a single color.
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub text: String,
+    pub color: Color,
+}
+
+/// One highlighted source line, without its trailing newline.
+pub type Line = Vec<Token>;
+
+struct Highlighter {
+    syntaxes: SyntaxSet,
+    theme: Theme,
+    cache: Mutex<Cache>,
+}
+
+/// Highlighted blocks by key, each with when it was last used.
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<u64, (Arc<Vec<Line>>, u64)>,
+    clock: u64,
+}
+
+impl Cache {
+    fn get(&mut self, key: u64) -> Option<Arc<Vec<Line>>> {
+        self.clock += 1;
+        let (lines, used) = self.entries.get_mut(&key)?;
+        *used = self.clock;
+        Some(lines.clone())
+    }
+
+    fn insert(&mut self, key: u64, lines: Arc<Vec<Line>>) {
+        if self.entries.len() >= MAX_CACHED {
+            let oldest = self.entries.iter().min_by_key(|(_, (_, used))| *used);
+            if let Some(&oldest) = oldest.map(|(key, _)| key) {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.clock += 1;
+        self.entries.insert(key, (lines, self.clock));
+    }
+}
+
+fn highlighter() -> &'static Highlighter {
+    static HIGHLIGHTER: OnceLock<Highlighter> = OnceLock::new();
+    HIGHLIGHTER.get_or_init(|| {
+        let mut themes = ThemeSet::load_defaults();
+        Highlighter {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+            theme: themes.themes.remove(THEME).unwrap_or_default(),
+            cache: Mutex::new(Cache::default()),
+        }
+    })
+}
+
+/// Highlights `code` as `language` (a fence tag such as `rust` or `py`).
+/// Returns `None` when the language isn't recognised, so the caller can fall
+/// back to plain monospace.
+pub fn highlight(language: &str, code: &str) -> Option<Arc<Vec<Line>>> {
+    let highlighter = highlighter();
+    let syntax = highlighter.syntaxes.find_syntax_by_token(language)?;
+
+    let mut hasher = DefaultHasher::new();
+    language.hash(&mut hasher);
+    code.hash(&mut hasher);
+    let key = hasher.finish();
+
+    if let Some(lines) = highlighter.cache.lock().ok()?.get(key) {
+        return Some(lines);
+    }
+
+    let mut lines = HighlightLines::new(syntax, &highlighter.theme);
+    let mut output = Vec::new();
+    for source_line in LinesWithEndings::from(code) {
+        let ranges = lines
+            .highlight_line(source_line, &highlighter.syntaxes)
+            .ok()?;
+        output.push(
+            ranges
+                .into_iter()
+                .map(|(style, text)| Token {
+                    text: text.trim_end_matches('\n').to_string(),
+                    color: Color::from_rgb8(
+                        style.foreground.r,
+                        style.foreground.g,
+                        style.foreground.b,
+                    ),
+                })
+                .filter(|token| !token.text.is_empty())
+                .collect(),
+        );
+    }
+
+    let output = Arc::new(output);
+    highlighter.cache.lock().ok()?.insert(key, output.clone());
+    Some(output)
+}
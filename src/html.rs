@@ -0,0 +1,51 @@
+//! The single-file HTML export shared by the GUI and `export --format html`.
+
+use crate::note::Note;
+
+/// Renders `notes`, in the given order, as cards in a single self-contained
+/// HTML document.
+pub fn render(notes: &[&Note]) -> String {
+    let cards: String = notes
+        .iter()
+        .map(|note| {
+            let [r, g, b] = note.color.map(|channel| (channel * 255.0).round() as u8);
+            format!(
+                "<article class=\"note\" style=\"background: rgb({}, {}, {})\">\n<h2>{}</h2>\n<p>{}</p>\n</article>\n",
+                r,
+                g,
+                b,
+                escape(&note.title),
+                if note.encrypted {
+                    "🔒 Locked".to_string()
+                } else {
+                    escape(&note.content)
+                },
+            )
+        })
+        .collect();
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Notes</title>\n<style>\n\
+         body {{ font-family: -apple-system, sans-serif; margin: 2em; background: #f4f4f4; }}\n\
+         .note {{ border-radius: 5px; padding: 1em; margin-bottom: 1em; color: #000; }}\n\
+         .note h2 {{ margin-top: 0; }}\n\
+         .note p {{ white-space: pre-wrap; }}\n\
+         </style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        cards
+    )
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
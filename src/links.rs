@@ -0,0 +1,188 @@
+//! `[[Note Title]]` links between notes.
+
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeSet, HashMap};
+
+use crate::note::Note;
+
+/// A run of plain text, or the title inside a `[[…]]` link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Piece<'a> {
+    Text(&'a str),
+    /// The title as written between the brackets, surrounding spaces and all.
+    Link(&'a str),
+}
+
+/// Splits `text` into plain runs and `[[title]]` links. Brackets around
+/// an empty title, or one spanning lines, are left as text.
+pub fn pieces(text: &str) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    let mut plain = 0;
+    while let Some(open) = rest[plain..].find("[[").map(|open| plain + open) {
+        let after = &rest[open + 2..];
+        let title = after
+            .find("]]")
+            .map(|close| &after[..close])
+            .filter(|title| !title.trim().is_empty() && !title.contains(['\n', '[', ']']));
+        match title {
+            Some(title) => {
+                if open > 0 {
+                    pieces.push(Piece::Text(&rest[..open]));
+                }
+                pieces.push(Piece::Link(title));
+                rest = &after[title.len() + 2..];
+                plain = 0;
+            }
+            None => plain = open + 1,
+        }
+    }
+    if !rest.is_empty() {
+        pieces.push(Piece::Text(rest));
+    }
+    pieces
+}
+
+/// The titles of every `[[…]]` link in `content`, in order.
+pub fn links(content: &str) -> impl Iterator<Item = &str> + '_ {
+    pieces(content).into_iter().filter_map(|piece| match piece {
+        Piece::Link(title) => Some(title.trim()),
+        Piece::Text(_) => None,
+    })
+}
+
+/// Rewrites every link to `old` in `content` to point at `new` instead,
+/// leaving everything else byte for byte as it was.
+pub fn retarget(content: &str, old: &str, new: &str) -> String {
+    let old = key(old);
+    pieces(content)
+        .into_iter()
+        .map(|piece| match piece {
+            Piece::Text(text) => text.to_string(),
+            Piece::Link(title) if key(title) == old => format!("[[{}]]", new.trim()),
+            Piece::Link(title) => format!("[[{}]]", title),
+        })
+        .collect()
+}
+
+/// How titles are compared: trimmed and ignoring case.
+fn key(title: &str) -> String {
+    title.trim().to_lowercase()
+}
+
+/// What a note looked like when it was last indexed. Anything that changes
+/// a note's text also bumps `updated_at` or flips `encrypted`.
+#[derive(Debug)]
+struct Indexed {
+    title: String,
+    updated_at: DateTime<Utc>,
+    encrypted: bool,
+    /// Keys of the titles the note links to.
+    targets: BTreeSet<String>,
+}
+
+/// Resolves link titles to note ids and lists the notes linking to each
+/// title, both case-insensitively.
+#[derive(Debug, Default)]
+pub struct LinkIndex {
+    /// Title key → id.
+    ids: HashMap<String, String>,
+    /// Title key → ids of the notes linking to it.
+    backlinks: HashMap<String, BTreeSet<String>>,
+    notes: HashMap<String, Indexed>,
+}
+
+impl LinkIndex {
+    /// Brings the index up to date with `notes`, re-reading the links of
+    /// only the notes that changed since the last call.
+    pub fn sync(&mut self, notes: &HashMap<String, Note>) {
+        let stale: Vec<String> = self
+            .notes
+            .keys()
+            .filter(|id| !notes.contains_key(*id))
+            .cloned()
+            .collect();
+        let mut retitled = !stale.is_empty();
+        for id in stale {
+            self.remove(&id);
+        }
+
+        for note in notes.values() {
+            let indexed = self.notes.get(&note.id);
+            retitled |= indexed.is_none_or(|indexed| indexed.title != note.title);
+            let unchanged = indexed.is_some_and(|indexed| {
+                indexed.updated_at == note.updated_at
+                    && indexed.encrypted == note.encrypted
+                    && indexed.title == note.title
+            });
+            if !unchanged {
+                self.insert(note);
+            }
+        }
+
+        if retitled {
+            self.ids.clear();
+            // Among notes sharing a title, the oldest always wins.
+            let mut notes: Vec<&Note> = notes.values().collect();
+            notes.sort_by_key(|note| (note.created_at, &note.id));
+            for note in notes {
+                self.ids
+                    .entry(key(&note.title))
+                    .or_insert_with(|| note.id.clone());
+            }
+        }
+    }
+
+    fn insert(&mut self, note: &Note) {
+        self.remove(&note.id);
+        // A locked note's links are hidden in its ciphertext.
+        let targets: BTreeSet<String> = if note.encrypted {
+            BTreeSet::new()
+        } else {
+            links(&note.content).map(key).collect()
+        };
+        for target in &targets {
+            self.backlinks
+                .entry(target.clone())
+                .or_default()
+                .insert(note.id.clone());
+        }
+        self.notes.insert(
+            note.id.clone(),
+            Indexed {
+                title: note.title.clone(),
+                updated_at: note.updated_at,
+                encrypted: note.encrypted,
+                targets,
+            },
+        );
+    }
+
+    fn remove(&mut self, id: &str) {
+        let Some(indexed) = self.notes.remove(id) else {
+            return;
+        };
+        for target in indexed.targets {
+            if let Some(sources) = self.backlinks.get_mut(&target) {
+                sources.remove(id);
+                if sources.is_empty() {
+                    self.backlinks.remove(&target);
+                }
+            }
+        }
+    }
+
+    /// The id of the note titled `title`, ignoring case.
+    pub fn resolve(&self, title: &str) -> Option<&str> {
+        self.ids.get(&key(title)).map(String::as_str)
+    }
+
+    /// Ids of the notes linking to `title`, in a stable order.
+    pub fn backlinks(&self, title: &str) -> impl Iterator<Item = &str> + '_ {
+        self.backlinks
+            .get(&key(title))
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+}
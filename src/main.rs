@@ -1,233 +1,91 @@
-use iced::widget::{button, column, container, row, scrollable, text, text_input};
-use iced::{Application, Color, Command, Element, Length, Settings, Theme};
-use iced::theme::Button as ButtonTheme;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs;
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-enum NoteColor {
-    Red,
-    Green,
-    Blue,
-    Yellow,
-    Orange,
-}
-
-impl NoteColor {
-    fn to_color(&self) -> Color {
-        match self {
-            NoteColor::Red => Color::from_rgb(1.0, 0.8, 0.8),
-            NoteColor::Green => Color::from_rgb(0.8, 1.0, 0.8),
-            NoteColor::Blue => Color::from_rgb(0.8, 0.8, 1.0),
-            NoteColor::Yellow => Color::from_rgb(1.0, 1.0, 0.8),
-            NoteColor::Orange => Color::from_rgb(1.0, 0.9, 0.8),
+use iced::{window, Application, Point, Settings, Size};
+use std::path::PathBuf;
+
+use app::{NotesApp, MAX_FONT_SIZE, MIN_FONT_SIZE};
+use storage::WindowGeometry;
+
+mod app;
+mod checklist;
+mod cli;
+mod crypto;
+mod highlight;
+mod html;
+mod links;
+mod markdown;
+mod menu;
+mod message;
+mod note;
+mod palette;
+mod replace;
+mod search;
+mod storage;
+mod style;
+mod toast;
+mod trash;
+mod whats_new;
+
+/// Bounds applied to a restored window so a stale geometry (e.g. from a
+/// monitor that is no longer connected) can't open the window off-screen.
+const MIN_WINDOW_SIZE: Size<u32> = Size::new(400, 300);
+const MAX_WINDOW_SIZE: Size<u32> = Size::new(5120, 2880);
+
+/// Builds the initial window settings from the geometry saved last session,
+/// falling back to iced's defaults when nothing was saved.
+fn window_settings(geometry: Option<WindowGeometry>) -> window::Settings {
+    // Closing is handled in `Message::CloseRequested` so unsaved edits can
+    // be confirmed first.
+    let defaults = window::Settings {
+        exit_on_close_request: false,
+        ..window::Settings::default()
+    };
+    let Some(geometry) = geometry else {
+        return defaults;
+    };
+
+    let width = geometry
+        .width
+        .clamp(MIN_WINDOW_SIZE.width, MAX_WINDOW_SIZE.width);
+    let height = geometry
+        .height
+        .clamp(MIN_WINDOW_SIZE.height, MAX_WINDOW_SIZE.height);
+    // Without querying monitors before launch, only trust positions that
+    // keep the window's top-left corner within the largest supported screen.
+    let position = match geometry.position {
+        Some((x, y))
+            if (0..MAX_WINDOW_SIZE.width as i32 - width as i32).contains(&x)
+                && (0..MAX_WINDOW_SIZE.height as i32 - height as i32).contains(&y) =>
+        {
+            window::Position::Specific(Point::new(x as f32, y as f32))
         }
-    }
-}
+        _ => defaults.position,
+    };
 
-struct NotesApp {
-    notes: HashMap<String, Note>,
-    current_note: Option<String>,
-    error: Option<String>,
-}
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
-struct Note {
-    id: String,
-    title: String,
-    content: String,
-    color: NoteColor,
-}
-
-#[derive(Debug, Clone)]
-enum Message {
-    CreateNote,
-    SelectNote(String),
-    UpdateNoteTitle(String),
-    UpdateNoteContent(String),
-    ChangeNoteColor(NoteColor),
-    ImportNotes,
-    ExportNotes,
-    ClearError,
-}
-
-impl Application for NotesApp {
-    type Executor = iced::executor::Default;
-    type Message = Message;
-    type Theme = Theme;
-    type Flags = ();
-
-    fn new(_flags: ()) -> (Self, Command<Message>) {
-        (
-            Self {
-                notes: HashMap::new(),
-                current_note: None,
-                error: None,
-            },
-            Command::none(),
-        )
-    }
-
-    fn title(&self) -> String {
-        String::from("Multi-Notes App")
-    }
-
-    fn update(&mut self, message: Message) -> Command<Message> {
-        match message {
-            Message::CreateNote => {
-                let id = uuid::Uuid::new_v4().to_string();
-                let note = Note {
-                    id: id.clone(),
-                    title: "New Note".to_string(),
-                    content: String::new(),
-                    color: NoteColor::Yellow,
-                };
-                self.notes.insert(id.clone(), note);
-                self.current_note = Some(id);
-            }
-            Message::SelectNote(id) => {
-                self.current_note = Some(id);
-            }
-            Message::UpdateNoteTitle(title) => {
-                if let Some(id) = &self.current_note {
-                    if let Some(note) = self.notes.get_mut(id) {
-                        note.title = title;
-                    }
-                }
-            }
-            Message::UpdateNoteContent(content) => {
-                if let Some(id) = &self.current_note {
-                    if let Some(note) = self.notes.get_mut(id) {
-                        note.content = content;
-                    }
-                }
-            }
-            Message::ChangeNoteColor(color) => {
-                if let Some(id) = &self.current_note {
-                    if let Some(note) = self.notes.get_mut(id) {
-                        note.color = color;
-                    }
-                }
-            }
-            Message::ImportNotes => {
-                match self.import_notes() {
-                    Ok(_) => self.error = None,
-                    Err(e) => self.error = Some(e.to_string()),
-                }
-            }
-            Message::ExportNotes => {
-                match self.export_notes() {
-                    Ok(_) => self.error = None,
-                    Err(e) => self.error = Some(e.to_string()),
-                }
-            }
-            Message::ClearError => {
-                self.error = None;
-            }
-        }
-        Command::none()
-    }
-
-    fn view(&self) -> Element<Message> {
-        let notes_list = self.notes.values().fold(
-            column![].spacing(5),
-            |column, note| {
-                column.push(
-                    button(text(&note.title).size(16))
-                        .on_press(Message::SelectNote(note.id.clone()))
-                        .style(ButtonTheme::Custom(Box::new(NoteButtonStyle(note.color))))
-                        .padding(10),
-                )
-            },
-        );
-
-        let notes_list = scrollable(notes_list).height(Length::Fill);
-
-        let note_editor = if let Some(id) = &self.current_note {
-            if let Some(note) = self.notes.get(id) {
-                column![
-                    text_input("Title", &note.title)
-                        .on_input(Message::UpdateNoteTitle)
-                        .padding(10),
-                    text_input("Content", &note.content)
-                        .on_input(Message::UpdateNoteContent)
-                        .padding(10),
-                    row![
-                        button("Red").on_press(Message::ChangeNoteColor(NoteColor::Red)),
-                        button("Green").on_press(Message::ChangeNoteColor(NoteColor::Green)),
-                        button("Blue").on_press(Message::ChangeNoteColor(NoteColor::Blue)),
-                        button("Yellow").on_press(Message::ChangeNoteColor(NoteColor::Yellow)),
-                        button("Orange").on_press(Message::ChangeNoteColor(NoteColor::Orange)),
-                    ]
-                    .spacing(5),
-                ]
-                .spacing(10)
-            } else {
-                column![text("Note not found")]
-            }
-        } else {
-            column![text("Select a note to edit")]
-        };
-
-        let content = row![
-            notes_list.width(Length::FillPortion(1)),
-            note_editor.width(Length::FillPortion(3)),
-        ]
-        .spacing(20);
-
-        let controls = row![
-            button("New Note").on_press(Message::CreateNote),
-            button("Import").on_press(Message::ImportNotes),
-            button("Export").on_press(Message::ExportNotes),
-        ]
-        .spacing(10);
-
-        let mut layout = column![content, controls].padding(20).spacing(20);
-
-        if let Some(error) = &self.error {
-            layout = layout.push(
-                container(text(error).style(Color::from_rgb(0.8, 0.0, 0.0)))
-                    .padding(10),
-            );
-        }
-
-        container(layout)
-            .width(Length::Fill)
-            .height(Length::Fill)
-            .center_x()
-            .into()
-    }
-}
-
-struct NoteButtonStyle(NoteColor);
-
-impl button::StyleSheet for NoteButtonStyle {
-    type Style = Theme;
-
-    fn active(&self, _style: &Self::Style) -> button::Appearance {
-        button::Appearance {
-            background: Some(iced::Background::Color(self.0.to_color())),
-            border_radius: 5.0,
-            ..Default::default()
-        }
+    window::Settings {
+        size: Size::new(width as f32, height as f32),
+        position,
+        ..defaults
     }
 }
 
-impl NotesApp {
-    fn import_notes(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let json = fs::read_to_string("notes.json")?;
-        self.notes = serde_json::from_str(&json)?;
-        Ok(())
+fn main() -> iced::Result {
+    let mut args = std::env::args_os().skip(1).peekable();
+    if args.peek().is_some_and(|arg| arg == "export") {
+        args.next();
+        std::process::exit(cli::export(args));
     }
 
-    fn export_notes(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let json = serde_json::to_string(&self.notes)?;
-        fs::write("notes.json", json)?;
-        Ok(())
-    }
+    let preferences = storage::load_preferences();
+    NotesApp::run(Settings {
+        // `open_source_note_app_macos [NOTES_FILE]` keeps a separate set of
+        // notes, e.g. one for work and one for home.
+        flags: args.next().map(PathBuf::from),
+        window: window_settings(preferences.window),
+        // iced's text_editor has no per-widget size, so the content editor
+        // picks up the saved zoom through the default text size at launch.
+        default_text_size: preferences
+            .font_size
+            .clamp(MIN_FONT_SIZE, MAX_FONT_SIZE)
+            .into(),
+        ..Settings::default()
+    })
 }
-
-fn main() -> iced::Result {
-    NotesApp::run(Settings::default())
-}
\ No newline at end of file
@@ -0,0 +1,427 @@
+//! Renders Markdown note content into iced widgets for the preview pane.
+
+use iced::alignment::Horizontal;
+use iced::font::{Style, Weight};
+use iced::widget::{
+    button, checkbox, container, horizontal_rule, horizontal_space, row, text, Column, Row,
+};
+use iced::{Color, Element, Font, Length};
+use pulldown_cmark::{
+    Alignment, CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd, TextMergeStream,
+};
+
+use crate::highlight;
+use crate::links::{self, Piece};
+
+const BODY_SIZE: u16 = 16;
+const LIST_INDENT: f32 = 20.0;
+const LINK_COLOR: Color = Color::from_rgb(0.2, 0.4, 0.9);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct SpanStyle {
+    bold: bool,
+    italic: bool,
+    code: bool,
+}
+
+impl SpanStyle {
+    fn font(self, body: Font) -> Font {
+        let base = if self.code { Font::MONOSPACE } else { body };
+        Font {
+            weight: if self.bold {
+                Weight::Bold
+            } else {
+                Weight::Normal
+            },
+            style: if self.italic {
+                Style::Italic
+            } else {
+                Style::Normal
+            },
+            ..base
+        }
+    }
+}
+
+/// Where a clickable span leads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Link {
+    Url(String),
+    /// The title inside a `[[…]]` link to another note.
+    Note(String),
+}
+
+struct Span {
+    text: String,
+    style: SpanStyle,
+    /// Destination, when the span is the text of a link.
+    link: Option<Link>,
+}
+
+/// A GFM table being collected cell by cell.
+struct Table<'a, Message> {
+    alignments: Vec<Alignment>,
+    rows: Vec<Vec<Element<'a, Message>>>,
+    row: Vec<Element<'a, Message>>,
+}
+
+/// Accumulates inline spans until a block ends, then pushes the finished
+/// block onto the output column.
+struct Renderer<'a, Message> {
+    on_link: fn(String) -> Message,
+    on_note_link: fn(String) -> Message,
+    on_task: fn(usize) -> Message,
+    /// Byte offsets in the source of the task-list checkboxes not reached
+    /// yet, in document order.
+    task_offsets: std::vec::IntoIter<usize>,
+    /// The checkbox of the current list item, if it's a task: whether it's
+    /// ticked and its byte offset.
+    task: Option<(bool, usize)>,
+    /// Font of everything but inline code and code blocks.
+    font: Font,
+    blocks: Column<'a, Message>,
+    spans: Vec<Span>,
+    style: SpanStyle,
+    heading: Option<HeadingLevel>,
+    lists: Vec<Option<u64>>,
+    item_marker: Option<String>,
+    code_block: Option<String>,
+    /// Fence tag of the current code block, if it had one.
+    code_language: Option<String>,
+    link: Option<Link>,
+    table: Option<Table<'a, Message>>,
+}
+
+impl<'a, Message: Clone + 'a> Renderer<'a, Message> {
+    fn new(
+        font: Font,
+        on_link: fn(String) -> Message,
+        on_note_link: fn(String) -> Message,
+        on_task: fn(usize) -> Message,
+        task_offsets: Vec<usize>,
+    ) -> Self {
+        Self {
+            on_link,
+            on_note_link,
+            on_task,
+            task_offsets: task_offsets.into_iter(),
+            task: None,
+            font,
+            blocks: Column::new().spacing(10),
+            spans: Vec::new(),
+            style: SpanStyle::default(),
+            heading: None,
+            lists: Vec::new(),
+            item_marker: None,
+            code_block: None,
+            code_language: None,
+            link: None,
+            table: None,
+        }
+    }
+
+    fn push_text(&mut self, value: &str, style: SpanStyle) {
+        if let Some(code) = &mut self.code_block {
+            code.push_str(value);
+            return;
+        }
+        match self.spans.last_mut() {
+            Some(span) if span.style == style && span.link == self.link => {
+                span.text.push_str(value)
+            }
+            _ => self.spans.push(Span {
+                text: value.to_string(),
+                style,
+                link: self.link.clone(),
+            }),
+        }
+    }
+
+    /// Lays out the pending spans as one line, links as clickable text.
+    fn take_line(&mut self, size: u16) -> Row<'a, Message> {
+        let (on_link, on_note_link) = (self.on_link, self.on_note_link);
+        let font = self.font;
+        std::mem::take(&mut self.spans)
+            .into_iter()
+            .fold(Row::new(), |line, span| {
+                let label = text(span.text).size(size).font(span.style.font(font));
+                let message = match span.link {
+                    Some(Link::Url(url)) => Some(on_link(url)),
+                    Some(Link::Note(title)) => Some(on_note_link(title)),
+                    None => None,
+                };
+                match message {
+                    Some(message) => line.push(
+                        button(label.style(LINK_COLOR))
+                            .on_press(message)
+                            .padding(0)
+                            .style(iced::theme::Button::Text),
+                    ),
+                    None => line.push(label),
+                }
+            })
+    }
+
+    fn flush(&mut self, size: u16) {
+        if self.spans.is_empty() {
+            return;
+        }
+        let line = self.take_line(size);
+
+        let block: Element<'a, Message> = if self.lists.is_empty() {
+            line.into()
+        } else {
+            let marker = self.item_marker.take().unwrap_or_default();
+            let marker: Element<'a, Message> = match self.task.take() {
+                Some((checked, offset)) => {
+                    let on_task = self.on_task;
+                    checkbox("", checked)
+                        .on_toggle(move |_| on_task(offset))
+                        .size(size)
+                        .into()
+                }
+                None => text(marker).size(size).width(LIST_INDENT).into(),
+            };
+            row![
+                horizontal_space().width(LIST_INDENT * self.lists.len() as f32),
+                marker,
+                line,
+            ]
+            .into()
+        };
+        self.push_block(block);
+    }
+
+    fn push_block(&mut self, block: impl Into<Element<'a, Message>>) {
+        self.blocks = std::mem::replace(&mut self.blocks, Column::new()).push(block);
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                self.flush(BODY_SIZE);
+                self.heading = Some(level);
+                self.style.bold = true;
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                let size = heading_size(self.heading.take());
+                self.flush(size);
+                self.style.bold = false;
+            }
+            Event::End(TagEnd::Paragraph) | Event::End(TagEnd::Item) => self.flush(BODY_SIZE),
+            Event::Start(Tag::List(first)) => {
+                self.flush(BODY_SIZE);
+                self.lists.push(first);
+            }
+            Event::End(TagEnd::List(_)) => {
+                self.lists.pop();
+            }
+            Event::Start(Tag::Item) => {
+                self.item_marker = Some(match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        let marker = format!("{}.", number);
+                        *number += 1;
+                        marker
+                    }
+                    _ => "•".to_string(),
+                });
+            }
+            Event::TaskListMarker(checked) => {
+                self.task = self.task_offsets.next().map(|offset| (checked, offset));
+            }
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                self.link = Some(Link::Url(dest_url.to_string()))
+            }
+            Event::End(TagEnd::Link) => self.link = None,
+            Event::Start(Tag::Table(alignments)) => {
+                self.flush(BODY_SIZE);
+                self.table = Some(Table {
+                    alignments,
+                    rows: Vec::new(),
+                    row: Vec::new(),
+                });
+            }
+            Event::Start(Tag::TableHead) => self.style.bold = true,
+            Event::End(TagEnd::TableCell) => {
+                let cell = self.take_line(BODY_SIZE);
+                if let Some(table) = &mut self.table {
+                    table.row.push(cell.into());
+                }
+            }
+            Event::End(TagEnd::TableHead) | Event::End(TagEnd::TableRow) => {
+                self.style.bold = false;
+                if let Some(table) = &mut self.table {
+                    let row = std::mem::take(&mut table.row);
+                    table.rows.push(row);
+                }
+            }
+            Event::End(TagEnd::Table) => {
+                if let Some(table) = self.table.take() {
+                    self.push_block(table_view(table));
+                }
+            }
+            Event::Start(Tag::Strong) => self.style.bold = true,
+            Event::End(TagEnd::Strong) => self.style.bold = self.heading.is_some(),
+            Event::Start(Tag::Emphasis) => self.style.italic = true,
+            Event::End(TagEnd::Emphasis) => self.style.italic = false,
+            Event::Start(Tag::CodeBlock(kind)) => {
+                self.flush(BODY_SIZE);
+                self.code_block = Some(String::new());
+                self.code_language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().map(str::to_string)
+                    }
+                    CodeBlockKind::Indented => None,
+                };
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some(code) = self.code_block.take() {
+                    let language = self.code_language.take();
+                    let block = container(code_view(language.as_deref(), &code))
+                        .padding(10)
+                        .style(iced::theme::Container::Box);
+                    self.push_block(block);
+                }
+            }
+            Event::Text(value) => {
+                let style = self.style;
+                if self.code_block.is_some() || self.link.is_some() {
+                    self.push_text(&value, style);
+                    return;
+                }
+                for piece in links::pieces(&value) {
+                    match piece {
+                        Piece::Text(text) => self.push_text(text, style),
+                        Piece::Link(title) => {
+                            let title = title.trim();
+                            self.link = Some(Link::Note(title.to_string()));
+                            self.push_text(title, style);
+                            self.link = None;
+                        }
+                    }
+                }
+            }
+            Event::Code(value) => {
+                let style = SpanStyle {
+                    code: true,
+                    ..self.style
+                };
+                self.push_text(&value, style);
+            }
+            Event::SoftBreak => {
+                let style = self.style;
+                self.push_text(" ", style);
+            }
+            Event::HardBreak => {
+                let style = self.style;
+                self.push_text("\n", style);
+            }
+            Event::Rule => {
+                self.flush(BODY_SIZE);
+                self.push_block(horizontal_rule(1));
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(mut self) -> Element<'a, Message> {
+        self.flush(BODY_SIZE);
+        self.blocks.into()
+    }
+}
+
+/// Lays out a table's rows as equal-width columns, aligned as the table's
+/// delimiter row asks. Short rows are padded so every column lines up.
+fn table_view<'a, Message: 'a>(table: Table<'a, Message>) -> Element<'a, Message> {
+    let columns = table
+        .rows
+        .iter()
+        .map(Vec::len)
+        .max()
+        .unwrap_or_default()
+        .max(table.alignments.len());
+    let alignments = table.alignments;
+    let rows = table.rows.into_iter().map(|cells| {
+        let mut cells = cells.into_iter();
+        (0..columns).fold(Row::new().spacing(10), |row, column| {
+            let cell = cells.next().unwrap_or_else(|| text("").into());
+            let align = match alignments.get(column) {
+                Some(Alignment::Center) => Horizontal::Center,
+                Some(Alignment::Right) => Horizontal::Right,
+                _ => Horizontal::Left,
+            };
+            row.push(container(cell).width(Length::FillPortion(1)).align_x(align))
+        })
+    });
+    container(rows.fold(Column::new().spacing(5), Column::push))
+        .padding(10)
+        .style(iced::theme::Container::Box)
+        .into()
+}
+
+/// Lays out a code block, highlighted when its fence names a known language.
+fn code_view<'a, Message: 'a>(language: Option<&str>, code: &str) -> Element<'a, Message> {
+    let size = BODY_SIZE - 2;
+    let Some(lines) = language.and_then(|language| highlight::highlight(language, code)) else {
+        return text(code.trim_end_matches('\n'))
+            .size(size)
+            .font(Font::MONOSPACE)
+            .into();
+    };
+
+    lines
+        .iter()
+        .fold(Column::new(), |column, line| {
+            let line = if line.is_empty() {
+                Row::new().push(text(" ").size(size).font(Font::MONOSPACE))
+            } else {
+                line.iter().fold(Row::new(), |row, token| {
+                    row.push(
+                        text(token.text.clone())
+                            .size(size)
+                            .font(Font::MONOSPACE)
+                            .style(token.color),
+                    )
+                })
+            };
+            column.push(line)
+        })
+        .into()
+}
+
+fn heading_size(level: Option<HeadingLevel>) -> u16 {
+    match level {
+        Some(HeadingLevel::H1) => 28,
+        Some(HeadingLevel::H2) => 24,
+        Some(HeadingLevel::H3) => 20,
+        _ => 18,
+    }
+}
+
+/// Parses `source` as Markdown and lays it out as a column of widgets in
+/// `font`. Clicking a link produces `on_link` with its destination,
+/// clicking a `[[title]]` link produces `on_note_link` with the title, and
+/// ticking a task-list checkbox produces `on_task` with the byte offset of
+/// its `[ ]` in `source`.
+pub fn view<'a, Message: Clone + 'a>(
+    source: &str,
+    font: Font,
+    on_link: fn(String) -> Message,
+    on_note_link: fn(String) -> Message,
+    on_task: fn(usize) -> Message,
+) -> Element<'a, Message> {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS;
+    // Merging text below loses the source ranges, so the checkboxes are
+    // located in a pass of their own.
+    let task_offsets = Parser::new_ext(source, options)
+        .into_offset_iter()
+        .filter(|(event, _)| matches!(event, Event::TaskListMarker(_)))
+        .map(|(_, range)| range.start + source[range].find('[').unwrap_or_default())
+        .collect();
+    let mut renderer = Renderer::new(font, on_link, on_note_link, on_task, task_offsets);
+    // Merged, so brackets split into separate text events still form links.
+    for event in TextMergeStream::new(Parser::new_ext(source, options)) {
+        renderer.event(event);
+    }
+    renderer.finish()
+}
@@ -0,0 +1,162 @@
+//! The application menu bar: File, Edit and View menus, and the keyboard
+//! accelerators shown beside their items.
+//!
+//! iced has no native menu support, so the bar is drawn in the window. The
+//! table here is the single source of truth for which command shortcut
+//! triggers which action; `app::handle_event` looks shortcuts up in it.
+
+use crate::message::Message;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Menu {
+    File,
+    Edit,
+    View,
+}
+
+/// A Command-key accelerator, e.g. ⇧⌘Z.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shortcut {
+    /// Lowercase key character.
+    pub key: &'static str,
+    pub shift: bool,
+}
+
+impl Shortcut {
+    const fn command(key: &'static str) -> Self {
+        Self { key, shift: false }
+    }
+
+    const fn command_shift(key: &'static str) -> Self {
+        Self { key, shift: true }
+    }
+
+    /// The accelerator as macOS menus write it.
+    pub fn label(self) -> String {
+        format!(
+            "{}⌘{}",
+            if self.shift { "⇧" } else { "" },
+            self.key.to_uppercase()
+        )
+    }
+}
+
+pub struct MenuItem {
+    pub label: &'static str,
+    pub shortcut: Option<Shortcut>,
+    pub message: Message,
+}
+
+fn item(label: &'static str, shortcut: Option<Shortcut>, message: Message) -> MenuItem {
+    MenuItem {
+        label,
+        shortcut,
+        message,
+    }
+}
+
+impl Menu {
+    pub const ALL: [Menu; 3] = [Menu::File, Menu::Edit, Menu::View];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Menu::File => "File",
+            Menu::Edit => "Edit",
+            Menu::View => "View",
+        }
+    }
+
+    pub fn items(self) -> Vec<MenuItem> {
+        match self {
+            Menu::File => vec![
+                item(
+                    "New Note",
+                    Some(Shortcut::command("n")),
+                    Message::CreateNote,
+                ),
+                item(
+                    "Save Note",
+                    Some(Shortcut::command("s")),
+                    Message::SaveCurrent,
+                ),
+                item("Import…", None, Message::ImportNotes),
+                item("Import Folder…", None, Message::ImportDirectory),
+                item("Export…", None, Message::ExportNotes),
+                item("Export Visible…", None, Message::ExportVisible),
+                item("Export Markdown…", None, Message::ExportCurrentMarkdown),
+                item("Export HTML…", None, Message::ExportHtml),
+                item("Export Archive…", None, Message::ExportArchive),
+                item(
+                    "Settings…",
+                    Some(Shortcut::command(",")),
+                    Message::OpenSettings,
+                ),
+            ],
+            Menu::Edit => vec![
+                item("Undo", Some(Shortcut::command("z")), Message::Undo),
+                item("Redo", Some(Shortcut::command_shift("z")), Message::Redo),
+                item(
+                    "Next Color",
+                    Some(Shortcut::command("k")),
+                    Message::CycleNoteColor,
+                ),
+                item("Delete Note", None, Message::DeleteSelected),
+                item("Find…", Some(Shortcut::command("f")), Message::FocusSearch),
+                item(
+                    "Replace…",
+                    Some(Shortcut::command("h")),
+                    Message::ToggleFindBar,
+                ),
+            ],
+            Menu::View => vec![
+                item(
+                    "Command Palette…",
+                    Some(Shortcut::command("p")),
+                    Message::TogglePalette,
+                ),
+                item("Toggle Theme", None, Message::ToggleTheme),
+                item("Toggle Preview", None, Message::TogglePreview),
+                item(
+                    "Focus Mode",
+                    Some(Shortcut::command_shift("f")),
+                    Message::ToggleZen,
+                ),
+                item(
+                    "Zoom In",
+                    Some(Shortcut::command("=")),
+                    Message::IncreaseFont,
+                ),
+                item(
+                    "Zoom Out",
+                    Some(Shortcut::command("-")),
+                    Message::DecreaseFont,
+                ),
+                item(
+                    "Actual Size",
+                    Some(Shortcut::command("0")),
+                    Message::ResetZoom,
+                ),
+            ],
+        }
+    }
+}
+
+/// Finds the menu action bound to Command+`key` (with Shift if `shift`).
+pub fn shortcut_message(key: &str, shift: bool) -> Option<Message> {
+    let key = key.to_lowercase();
+    // ⌘+ is how most people reach Zoom In, though the key is really "=" and
+    // typing "+" usually takes Shift.
+    let (key, shift) = if key == "+" {
+        ("=", false)
+    } else {
+        (key.as_str(), shift)
+    };
+    Menu::ALL
+        .into_iter()
+        .flat_map(Menu::items)
+        .find(|item| {
+            item.shortcut
+                .is_some_and(|shortcut| shortcut.key == key && shortcut.shift == shift)
+        })
+        .map(|item| item.message)
+}
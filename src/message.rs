@@ -0,0 +1,211 @@
+//! Messages produced by the UI and handled in `NotesApp::update`.
+
+use chrono::{DateTime, Utc};
+use iced::widget::text_editor;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::app::SortMode;
+use crate::menu::Menu;
+use crate::note::NoteColor;
+use crate::style::SidebarStyle;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ToggleMenu(Menu),
+    /// A menu entry was chosen: close the menu, then handle the message.
+    MenuItem(Box<Message>),
+    /// Opens the command palette, or closes it if it's open.
+    TogglePalette,
+    UpdatePalette(String),
+    /// Enter in the palette: runs the highlighted match.
+    SubmitPalette,
+    /// A palette entry was chosen: close the palette, then handle the message.
+    RunCommand(Box<Message>),
+    CreateNote,
+    SelectNote(String),
+    BeginRename(String),
+    UpdateRename(String),
+    CommitRename,
+    /// Backs out of the innermost thing open: the command palette, else the
+    /// find bar, else focus mode, else settings, else an in-place rename,
+    /// or else clears the search query.
+    Escape,
+    FocusSearch,
+    SelectPrevious,
+    SelectNext,
+    DuplicateNote(String),
+    UpdateNoteTitle(String),
+    /// Enter in the title field: trims the title, replacing a blank one.
+    CommitTitle,
+    EditContent(text_editor::Action),
+    ChangeNoteColor(NoteColor),
+    /// Moves the current note on to the next preset color.
+    CycleNoteColor,
+    SetCustomColor(f32, f32, f32),
+    ToggleChecklistItem(usize),
+    /// Opens the find/replace bar over the editor, or closes it if it's
+    /// open.
+    ToggleFindBar,
+    UpdateFind(String),
+    UpdateReplace(String),
+    ToggleMatchCase(bool),
+    /// Replaces the next match in the current note.
+    Replace,
+    /// Replaces every match in the current note at once.
+    ReplaceAll,
+    /// Flips the task-list checkbox at this byte offset of the content,
+    /// clicked in the preview.
+    ToggleTask(usize),
+    Undo,
+    Redo,
+    TogglePin(String),
+    ToggleFavorite(String),
+    /// Protects a note's title and content from edits, or lifts that.
+    ToggleLocked(String),
+    /// Switches a note's content between the proportional and monospace font.
+    ToggleMonospace(String),
+    MoveNoteUp(String),
+    MoveNoteDown(String),
+    /// Moves a note to the trash, offering to undo it for a few seconds.
+    RequestDelete(String),
+    DeleteSelected,
+    /// Takes a just-deleted note back out of the trash and reopens it
+    /// where it was.
+    UndoDelete(String),
+    CloseRequested,
+    SaveAndQuit,
+    DiscardAndQuit,
+    CancelQuit,
+    RestoreNote(String),
+    EmptyTrash,
+    ToggleTrash,
+    SetSortMode(SortMode),
+    UpdateSearch(String),
+    FilterByTag(Option<String>),
+    FilterByColor(Option<NoteColor>),
+    ToggleFavoritesFilter,
+    ToggleDueSoonFilter,
+    ToggleOverdueFirst,
+    UpdateDueInput(String),
+    SubmitDue,
+    SetDue(Option<DateTime<Utc>>),
+    UpdateTagInput(String),
+    AddTag(String),
+    RemoveTag(String),
+    SelectNotebook(Option<String>),
+    MoveNoteToNotebook(String, Option<String>),
+    UpdateNotebookInput(String),
+    TogglePreview,
+    /// Re-renders the preview of a note too large to preview live.
+    RefreshPreview,
+    /// Expands or collapses the current note's word-frequency panel.
+    ToggleInsights,
+    /// Lists the notes sharing the most words with the given one.
+    FindSimilar(String),
+    /// The cursor entered (`Some`) or left (`None`) a sidebar note.
+    HoverNote(Option<String>),
+    /// The hover delay for a note's peek has passed.
+    ShowPeek(String),
+    /// Puts a note's content on the clipboard.
+    CopyNote(String),
+    /// Puts a note on the clipboard with its title as an H1.
+    CopyNoteMarkdown(String),
+    CloseSimilar,
+    SetSimilarityThreshold(f32),
+    /// Switches the editor to an already open tab.
+    SelectTab(String),
+    CloseTab(String),
+    /// A link in the preview was clicked.
+    OpenUrl(String),
+    /// A `[[title]]` link in the preview was clicked.
+    FollowLink(String),
+    /// Creates a note with this title, offered when a link had no target.
+    CreateLinkedNote(String),
+    /// Expands or collapses the current note's "Linked from" panel.
+    ToggleBacklinks,
+    /// Points every `[[old]]` link at the new title: `(old, new)`.
+    RetargetLinks(String, String),
+    LockNote(String),
+    UpdatePassword(String),
+    ConfirmLock,
+    CancelLock,
+    UnlockNote(String, String),
+    ToggleTheme,
+    /// Enters or leaves focus mode, where only the editor shows.
+    ToggleZen,
+    ToggleReuseBlankNotes(bool),
+    ToggleSnippets(bool),
+    SetSidebarStyle(SidebarStyle),
+    /// Sets the seconds of quiet before autosave, 0 turning it off.
+    SetAutosaveInterval(u64),
+    /// Shows every preference in one pane in place of the notes.
+    OpenSettings,
+    CloseSettings,
+    /// Resets every preference to its default.
+    RestoreDefaultSettings,
+    /// Hides the "What's new" panel until the next version.
+    DismissWhatsNew,
+    IncreaseFont,
+    DecreaseFont,
+    ResetZoom,
+    /// Picks a notes file, then asks whether to replace or merge.
+    ImportNotes,
+    /// Replaces the open notes with the picked file's.
+    ImportReplace,
+    /// Adds the picked file's notes to the open ones.
+    ImportMerge,
+    /// Restores the notes saved aside before the last replacing import.
+    UndoImport,
+    CancelImport,
+    ImportDirectory,
+    ExportNotes,
+    /// Exports only the notes the sidebar currently shows.
+    ExportVisible,
+    ExportCurrentMarkdown,
+    ExportHtml,
+    /// Saves every note as its own Markdown file inside a zip archive.
+    ExportArchive,
+    /// Saves pending changes if editing has paused for the autosave interval.
+    FlushSave,
+    /// Writes only the current note to disk straight away.
+    SaveCurrent,
+    /// A background write of the notes file finished. `Ok` carries a
+    /// warning when the previous file couldn't be backed up first.
+    SaveComplete(Result<Option<String>, Arc<io::Error>>),
+    /// Claims the notes file from another running instance so saving
+    /// resumes here.
+    TakeOverLock,
+    /// The notes file changed on disk: write the open notes over it anyway.
+    OverwriteDisk,
+    /// The notes file changed on disk: merge its notes into the open ones.
+    ReloadFromDisk,
+    /// The notes file changed on disk: leave it, and the edits, as they are.
+    CancelConflict,
+    /// Picks a new notes file after the current one turned out read-only.
+    ChooseStorageLocation,
+    WindowUnfocused,
+    /// Files are being dragged over the window.
+    FilesHovered,
+    FilesHoveredLeft,
+    /// A file was dropped on the window: a notes file to import, or a
+    /// text file to add as a note.
+    FileDropped(PathBuf),
+    /// The sidebar/editor divider was pressed; a second press soon after
+    /// resets the split.
+    PressDivider,
+    /// The cursor moved to this x position while dragging the divider.
+    DragDivider(f32),
+    ReleaseDivider,
+    /// Sets the sidebar's share of the split, clamped to the allowed range.
+    ResizeSplit(f32),
+    WindowResized(u32, u32),
+    WindowMoved(i32, i32),
+    /// Closes the toast at this index.
+    Dismiss(usize),
+    /// A toast's timer fired; drops every toast shown for `TOAST_DURATION`.
+    ExpireToasts,
+    /// Redraws time-relative labels such as "edited 3 minutes ago".
+    Tick,
+}
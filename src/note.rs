@@ -0,0 +1,581 @@
+//! The `Note` type and its on-disk representation.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NoteColor {
+    Red,
+    Green,
+    Blue,
+    Yellow,
+    Orange,
+}
+
+impl NoteColor {
+    pub const ALL: [NoteColor; 5] = [
+        NoteColor::Red,
+        NoteColor::Green,
+        NoteColor::Blue,
+        NoteColor::Yellow,
+        NoteColor::Orange,
+    ];
+
+    /// The preset after this one in `ALL`, wrapping around to the first.
+    pub fn next(&self) -> NoteColor {
+        let index = NoteColor::ALL
+            .iter()
+            .position(|color| color == self)
+            .unwrap_or_default();
+        NoteColor::ALL[(index + 1) % NoteColor::ALL.len()]
+    }
+
+    pub fn to_rgb(self) -> [f32; 3] {
+        match self {
+            NoteColor::Red => [1.0, 0.8, 0.8],
+            NoteColor::Green => [0.8, 1.0, 0.8],
+            NoteColor::Blue => [0.8, 0.8, 1.0],
+            NoteColor::Yellow => [1.0, 1.0, 0.8],
+            NoteColor::Orange => [1.0, 0.9, 0.8],
+        }
+    }
+}
+
+/// Notes used to store one of the `NoteColor` presets by name; they now
+/// store an RGB triple. Accept both so older files keep loading.
+fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[f32; 3], D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StoredColor {
+        Rgb([f32; 3]),
+        Preset(NoteColor),
+    }
+
+    Ok(match StoredColor::deserialize(deserializer)? {
+        StoredColor::Rgb(rgb) => rgb,
+        StoredColor::Preset(preset) => preset.to_rgb(),
+    })
+}
+
+/// Title given to new notes until their first line provides a better one.
+pub const DEFAULT_TITLE: &str = "New Note";
+
+/// Longest title `derive_title` will produce, in characters.
+const DERIVED_TITLE_LEN: usize = 40;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Note {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub color: [f32; 3],
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notebook: Option<String>,
+    /// When set, `content` holds the output of `crypto::SealingKey::seal` rather
+    /// than the note text.
+    #[serde(default)]
+    pub encrypted: bool,
+    /// Position under `SortMode::Manual`.
+    #[serde(default)]
+    pub order: i64,
+    #[serde(default)]
+    pub favorite: bool,
+    /// Set once the user types a title, after which it no longer follows
+    /// the first line of the content.
+    #[serde(default)]
+    pub title_is_manual: bool,
+    #[serde(default)]
+    pub due: Option<DateTime<Utc>>,
+    /// Shows the content in a monospace font, for code or ASCII art.
+    #[serde(default)]
+    pub monospace: bool,
+    /// Protects the title and content from accidental edits. Unlike
+    /// `encrypted`, the content stays readable.
+    #[serde(default)]
+    pub locked: bool,
+    /// How many times the title or content has been changed, counting a
+    /// burst of typing once.
+    #[serde(default)]
+    pub revision: u64,
+    /// When the note was moved to the trash; `None` while it's live. The
+    /// trash is saved alongside the other notes so it survives a restart.
+    #[serde(default)]
+    pub trashed_at: Option<DateTime<Utc>>,
+}
+
+impl Note {
+    /// Creates a note with a fresh id and the default color.
+    pub fn new(title: impl Into<String>, content: String) -> Self {
+        let now = Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: title.into(),
+            content,
+            color: NoteColor::Yellow.to_rgb(),
+            created_at: now,
+            updated_at: now,
+            pinned: false,
+            tags: Vec::new(),
+            notebook: None,
+            encrypted: false,
+            order: 0,
+            favorite: false,
+            title_is_manual: false,
+            due: None,
+            monospace: false,
+            locked: false,
+            revision: 0,
+            trashed_at: None,
+        }
+    }
+
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        self.due.is_some_and(|due| due < now)
+    }
+
+    /// Whether the note falls due before `now + within`, overdue included.
+    pub fn is_due_within(&self, now: DateTime<Utc>, within: chrono::Duration) -> bool {
+        self.due.is_some_and(|due| due <= now + within)
+    }
+
+    /// A note nobody has written anything in yet.
+    pub fn is_blank(&self) -> bool {
+        !self.encrypted
+            && self.content.trim().is_empty()
+            && (self.title.is_empty() || self.title == DEFAULT_TITLE)
+    }
+
+    /// Replaces the content, updating the title from the new first line if
+    /// the title is still a placeholder or was itself derived automatically.
+    pub fn set_content(&mut self, content: String) {
+        let auto_title = !self.title_is_manual
+            && (self.title.is_empty()
+                || self.title == DEFAULT_TITLE
+                || derive_title(&self.content).as_ref() == Some(&self.title));
+        self.content = content;
+        if auto_title {
+            if let Some(title) = derive_title(&self.content) {
+                self.title = title;
+            }
+        }
+    }
+}
+
+/// A title taken from the first non-empty line of `content`, without any
+/// Markdown heading marker and cut to `DERIVED_TITLE_LEN` characters.
+pub fn derive_title(content: &str) -> Option<String> {
+    let line = content
+        .lines()
+        .map(|line| line.trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())?;
+    Some(truncate_display(line, DERIVED_TITLE_LEN))
+}
+
+/// Whether `c` draws onto the character before it instead of standing
+/// alone: combining marks, variation selectors, skin tone modifiers, tag
+/// characters and zero-width joiners.
+fn extends_cluster(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FE20}'..='\u{FE2F}'
+            | '\u{200D}'
+            | '\u{1F3FB}'..='\u{1F3FF}'
+            | '\u{E0020}'..='\u{E007F}'
+    )
+}
+
+/// Whether a cluster boundary falls between `previous` and `c`. Anything
+/// after a zero-width joiner belongs to the same emoji sequence.
+fn is_boundary(previous: Option<char>, c: char) -> bool {
+    previous.is_none_or(|previous| previous != '\u{200D}' && !extends_cluster(c))
+}
+
+/// Byte offsets where each visible character of `s` starts, keeping base
+/// characters together with the marks and joined emoji that follow them.
+fn cluster_starts(s: &str) -> impl Iterator<Item = usize> + '_ {
+    let mut previous = None;
+    s.char_indices().filter_map(move |(index, c)| {
+        let starts = is_boundary(previous, c);
+        previous = Some(c);
+        starts.then_some(index)
+    })
+}
+
+/// Cuts `s` to at most `max_chars` visible characters, the last being "…"
+/// when anything was cut. Never splits a character, nor a character from
+/// its combining marks or an emoji sequence.
+pub fn truncate_display(s: &str, max_chars: usize) -> String {
+    if cluster_starts(s).nth(max_chars).is_none() {
+        return s.to_string();
+    }
+    let end = max_chars
+        .checked_sub(1)
+        .and_then(|kept| cluster_starts(s).nth(kept))
+        .unwrap_or_default();
+    format!("{}…", s[..end].trim_end())
+}
+
+/// Title given to a note whose title is blank and whose content has no
+/// line to derive one from.
+pub const UNTITLED: &str = "Untitled";
+
+/// The title to commit for `title`: trimmed, or if nothing is left, one
+/// derived from `content`, or [`UNTITLED`].
+pub fn normalize_title(title: &str, content: &str) -> String {
+    match title.trim() {
+        "" => derive_title(content).unwrap_or_else(|| UNTITLED.to_string()),
+        title => title.to_string(),
+    }
+}
+
+/// Longest sidebar snippet, in characters, before it is cut with "…".
+const SNIPPET_LEN: usize = 60;
+/// Characters kept ahead of a search match that falls past the snippet.
+const SNIPPET_LEAD: usize = 20;
+/// How far into a note, in bytes, `snippet` looks for a search match, so
+/// the sidebar costs the same however long notes get. A match further in
+/// leaves the snippet showing the start of the note.
+const SNIPPET_SCAN_LEN: usize = 10_000;
+
+/// A one-line excerpt of a note's content for the sidebar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub text: String,
+    /// Byte range of the search match within `text`, if one is visible.
+    pub highlight: Option<Range<usize>>,
+}
+
+/// Collapses the whitespace in `chars` to single spaces, stopping after
+/// `limit` characters. Also says whether any words were left over.
+fn collapse(chars: impl Iterator<Item = char>, limit: usize) -> (String, bool) {
+    let mut line = String::new();
+    let mut count = 0;
+    let mut space = false;
+    for c in chars {
+        if c.is_whitespace() {
+            space = !line.is_empty();
+            continue;
+        }
+        if count >= limit {
+            return (line, true);
+        }
+        if space {
+            line.push(' ');
+            count += 1;
+            space = false;
+        }
+        line.push(c);
+        count += 1;
+    }
+    (line, false)
+}
+
+/// Collapses `content` onto one line and cuts it to about
+/// [`SNIPPET_LEN`] characters, sliding the window forward so the first
+/// case-insensitive match of `query` stays visible. Only the text around
+/// the match is looked at, so long notes cost no more than short ones.
+pub fn snippet(content: &str, query: &str) -> Snippet {
+    let query = query.trim();
+    let mut scan = SNIPPET_SCAN_LEN.min(content.len());
+    while !content.is_char_boundary(scan) {
+        scan -= 1;
+    }
+    let at = find_ignore_case(&content[..scan], query).map_or(0, |range| range.start);
+    let (before, after) = content.split_at(at);
+    // Up to a snippet's worth either side of the match is all the window
+    // below can show. A cut may split a cluster, so drop the broken one.
+    let (lead, cut_before) = collapse(before.chars().rev(), SNIPPET_LEN);
+    let mut lead: String = lead.chars().rev().collect();
+    if cut_before {
+        let whole = cluster_starts(&lead).nth(1).unwrap_or(lead.len());
+        lead.drain(..whole);
+    }
+    let (mut tail, cut_after) = collapse(after.chars(), 2 * SNIPPET_LEN);
+    if cut_after {
+        tail.truncate(cluster_starts(&tail).last().unwrap_or(0));
+    }
+    let joint = before.ends_with(char::is_whitespace) || after.starts_with(char::is_whitespace);
+    let line = if joint && !lead.is_empty() && !tail.is_empty() {
+        format!("{} {}", lead, tail)
+    } else {
+        lead + &tail
+    };
+    let found = find_ignore_case(&line, query);
+
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    let char_at = |byte: usize| chars.partition_point(|&(index, _)| index < byte);
+    // Window edges move outwards to cluster boundaries, so a cut never
+    // separates a letter from its accent or breaks up an emoji.
+    let at_boundary = |index: usize| {
+        index == 0 || index >= chars.len() || is_boundary(Some(chars[index - 1].1), chars[index].1)
+    };
+    let mut start = match &found {
+        Some(range) if char_at(range.end) > SNIPPET_LEN => {
+            char_at(range.start).saturating_sub(SNIPPET_LEAD)
+        }
+        _ => 0,
+    };
+    while !at_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (start + SNIPPET_LEN).min(chars.len());
+    while !at_boundary(end) {
+        end += 1;
+    }
+    let byte = |index: usize| chars.get(index).map_or(line.len(), |&(byte, _)| byte);
+    let (from, to) = (byte(start), byte(end));
+
+    let mut text = String::new();
+    if start > 0 || cut_before {
+        text.push('…');
+    }
+    let offset = text.len();
+    text.push_str(&line[from..to]);
+    if end < chars.len() || cut_after {
+        text.push('…');
+    }
+    let highlight = found
+        .filter(|range| range.start >= from && range.end <= to)
+        .map(|range| range.start - from + offset..range.end - from + offset);
+    Snippet { text, highlight }
+}
+
+/// Byte range of the first case-insensitive occurrence of `needle`.
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<Range<usize>> {
+    if needle.is_empty() {
+        return None;
+    }
+    let needle: Vec<char> = needle.chars().flat_map(char::to_lowercase).collect();
+    haystack.char_indices().find_map(|(start, _)| {
+        let mut rest = haystack[start..].char_indices();
+        let mut lowered = Vec::new();
+        let mut end = start;
+        while lowered.len() < needle.len() {
+            let (offset, c) = rest.next()?;
+            lowered.extend(c.to_lowercase());
+            end = start + offset + c.len_utf8();
+        }
+        (lowered == needle).then_some(start..end)
+    })
+}
+
+/// Renders a note as a standalone Markdown document: a small frontmatter
+/// block with its timestamps, the title as an H1, then the content.
+pub fn note_to_markdown(note: &Note) -> String {
+    format!(
+        "---\ncreated: {}\nupdated: {}\n---\n\n# {}\n\n{}\n",
+        note.created_at.to_rfc3339(),
+        note.updated_at.to_rfc3339(),
+        note.title,
+        note.content,
+    )
+}
+
+/// Returns the `(words, characters)` counts of a note's content.
+pub fn note_stats(content: &str) -> (usize, usize) {
+    (content.split_whitespace().count(), content.chars().count())
+}
+
+/// Average silent reading speed used for the reading-time estimate.
+const WORDS_PER_MINUTE: usize = 200;
+const TOP_WORDS: usize = 5;
+
+/// Words too common to say anything about a note.
+const STOPWORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be", "because",
+    "been", "but", "by", "can", "could", "did", "do", "does", "for", "from", "had", "has", "have",
+    "he", "her", "him", "his", "how", "i", "if", "in", "into", "is", "it", "its", "just", "me",
+    "my", "no", "not", "of", "on", "or", "our", "out", "she", "so", "some", "than", "that", "the",
+    "their", "them", "then", "there", "these", "they", "this", "to", "up", "us", "was", "we",
+    "were", "what", "when", "which", "who", "will", "with", "would", "you", "your",
+];
+
+/// What [`analyze`] found in a note's content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteInsights {
+    /// The most frequent non-stopwords with their counts, most frequent
+    /// first and alphabetical among ties.
+    pub top_words: Vec<(String, usize)>,
+    /// Estimated reading time, rounded up to whole minutes.
+    pub reading_minutes: usize,
+}
+
+/// Splits `content` into lowercase words, treating anything but letters,
+/// digits and in-word apostrophes as a separator.
+fn words(content: &str) -> impl Iterator<Item = String> + '_ {
+    content
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .map(|word| word.trim_matches('\''))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Finds the most frequent words in `content` and estimates how long it
+/// takes to read.
+pub fn analyze(content: &str) -> NoteInsights {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut total = 0usize;
+    for word in words(content) {
+        total += 1;
+        if !STOPWORDS.contains(&word.as_str()) {
+            *counts.entry(word).or_default() += 1;
+        }
+    }
+
+    let mut top_words: Vec<_> = counts.into_iter().collect();
+    top_words.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+    top_words.truncate(TOP_WORDS);
+    NoteInsights {
+        top_words,
+        reading_minutes: total.div_ceil(WORDS_PER_MINUTE),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_content_has_no_words_or_characters() {
+        assert_eq!(note_stats(""), (0, 0));
+        assert_eq!(note_stats("  \n\t "), (0, 5));
+        assert_eq!(
+            analyze(""),
+            NoteInsights {
+                top_words: Vec::new(),
+                reading_minutes: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn words_are_counted_across_lines() {
+        let content = "Rust is fast.\nRust is safe\n\nfast rust";
+        assert_eq!(note_stats(content), (8, content.len()));
+        assert_eq!(
+            analyze(content),
+            NoteInsights {
+                top_words: vec![
+                    ("rust".to_string(), 3),
+                    ("fast".to_string(), 2),
+                    ("safe".to_string(), 1),
+                ],
+                reading_minutes: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn characters_are_counted_not_bytes() {
+        assert_eq!(note_stats("caf\u{e9} na\u{ef}ve"), (2, 10));
+        assert_eq!(note_stats("日本語 テキスト"), (2, 8));
+        assert_eq!(
+            analyze("\u{c9}t\u{e9}, \u{e9}t\u{e9}! \u{c9}T\u{c9} \u{fc}ber don't").top_words,
+            vec![
+                ("\u{e9}t\u{e9}".to_string(), 3),
+                ("don't".to_string(), 1),
+                ("\u{fc}ber".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn words_split_on_punctuation_but_keep_apostrophes() {
+        let split: Vec<String> = words("Hello, world! It's—don't 'quoted' x2 2X").collect();
+        assert_eq!(
+            split,
+            ["hello", "world", "it's", "don't", "quoted", "x2", "2x"]
+        );
+    }
+
+    #[test]
+    fn stopwords_are_left_out_of_top_words_but_still_read() {
+        let insights = analyze("The cat and the hat. THE END");
+        assert_eq!(
+            insights.top_words,
+            vec![
+                ("cat".to_string(), 1),
+                ("end".to_string(), 1),
+                ("hat".to_string(), 1),
+            ]
+        );
+        assert_eq!(analyze("the and of").top_words, Vec::new());
+    }
+
+    #[test]
+    fn only_the_five_most_frequent_words_are_kept() {
+        let content = "one two two three three three four four four four \
+                       five five five five five six six six six six six";
+        let insights = analyze(content);
+        let top: Vec<&str> = insights
+            .top_words
+            .iter()
+            .map(|(word, _)| word.as_str())
+            .collect();
+        assert_eq!(top, ["six", "five", "four", "three", "two"]);
+    }
+
+    #[test]
+    fn reading_time_rounds_up_to_whole_minutes() {
+        assert_eq!(analyze(&"word ".repeat(200)).reading_minutes, 1);
+        assert_eq!(analyze(&"word ".repeat(201)).reading_minutes, 2);
+        assert_eq!(analyze(&"the ".repeat(401)).reading_minutes, 3);
+    }
+
+    #[test]
+    fn next_color_visits_every_preset_and_wraps_around() {
+        let mut color = NoteColor::Red;
+        let mut seen = Vec::new();
+        for _ in 0..NoteColor::ALL.len() {
+            seen.push(color);
+            color = color.next();
+        }
+        assert_eq!(seen, NoteColor::ALL);
+        assert_eq!(color, NoteColor::Red);
+        assert_eq!(NoteColor::Orange.next(), NoteColor::Red);
+    }
+
+    #[test]
+    fn text_that_fits_exactly_is_not_truncated() {
+        assert_eq!(truncate_display("hello", 5), "hello");
+        assert_eq!(truncate_display("hello!", 5), "hell…");
+        assert_eq!(truncate_display("", 0), "");
+        assert_eq!(truncate_display("ab   cd", 4), "ab…");
+    }
+
+    #[test]
+    fn combining_marks_stay_with_their_letter() {
+        let accented = "e\u{301}e\u{301}e\u{301}";
+        assert_eq!(truncate_display(accented, 3), accented);
+        assert_eq!(truncate_display(accented, 2), "e\u{301}…");
+    }
+
+    #[test]
+    fn emoji_sequences_are_never_split() {
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let text = format!("{family}ab");
+        assert_eq!(truncate_display(&text, 3), text);
+        assert_eq!(truncate_display(&text, 2), format!("{family}…"));
+
+        let thumbs = "\u{1F44D}\u{1F3FD}";
+        assert_eq!(truncate_display(&thumbs.repeat(3), 2), format!("{thumbs}…"));
+        let heart = "\u{2764}\u{FE0F}";
+        assert_eq!(truncate_display(&heart.repeat(2), 2), heart.repeat(2));
+    }
+}
@@ -0,0 +1,171 @@
+//! The command palette: every menu action and note behind one fuzzy
+//! search box.
+
+use iced::widget::{button, column, container, text, text_input};
+use iced::{Element, Length};
+
+use crate::menu::Menu;
+use crate::message::Message;
+use crate::note::Note;
+
+/// How many matches the palette lists at most.
+pub const MAX_RESULTS: usize = 10;
+
+/// The palette while it's open.
+#[derive(Debug, Default)]
+pub struct Palette {
+    pub query: String,
+    /// Index of the highlighted match.
+    pub selected: usize,
+}
+
+/// Scores how well `query` matches `candidate`, ignoring case: every query
+/// character has to appear in `candidate` in order. Runs of consecutive
+/// characters and matches at the start of words score extra, and shorter
+/// candidates win ties. `None` when it doesn't match at all. An empty
+/// query matches everything equally, so the palette lists its entries in
+/// their usual order before anything is typed.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let candidate: Vec<char> = candidate.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut matched = 0;
+    let mut last_match: Option<usize> = None;
+    for (index, &c) in candidate.iter().enumerate() {
+        if matched == query.len() {
+            break;
+        }
+        if c != query[matched] {
+            continue;
+        }
+        score += 1;
+        if last_match.is_some_and(|last| last + 1 == index) {
+            score += 5;
+        }
+        if index == 0 || !candidate[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        last_match = Some(index);
+        matched += 1;
+    }
+    (matched == query.len()).then(|| score * 100 - candidate.len() as i64)
+}
+
+/// Every action in the menus, then a jump to each of `notes`.
+pub fn entries(notes: &[&Note]) -> Vec<(String, Message)> {
+    let commands = Menu::ALL
+        .into_iter()
+        .flat_map(Menu::items)
+        .filter(|item| !matches!(item.message, Message::TogglePalette))
+        .map(|item| (item.label.trim_end_matches('…').to_string(), item.message));
+    let notes = notes.iter().map(|note| {
+        (
+            format!("Go to: {}", note.title),
+            Message::SelectNote(note.id.clone()),
+        )
+    });
+    commands.chain(notes).collect()
+}
+
+/// Ranks `entries` by how well their labels match `query`, best first,
+/// dropping those that don't match and keeping at most `MAX_RESULTS`.
+pub fn rank(query: &str, entries: Vec<(String, Message)>) -> Vec<(String, Message)> {
+    let mut scored: Vec<(i64, (String, Message))> = entries
+        .into_iter()
+        .filter_map(|entry| Some((fuzzy_score(query.trim(), &entry.0)?, entry)))
+        .collect();
+    // Stable, so equal scores keep the order they were listed in.
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored
+        .into_iter()
+        .take(MAX_RESULTS)
+        .map(|(_, entry)| entry)
+        .collect()
+}
+
+pub fn input_id() -> text_input::Id {
+    text_input::Id::new("palette")
+}
+
+/// The search box over the `matches`, the selected one highlighted.
+pub fn view<'a>(palette: &'a Palette, matches: Vec<(String, Message)>) -> Element<'a, Message> {
+    let input = text_input("Type a command or note title", &palette.query)
+        .id(input_id())
+        .on_input(Message::UpdatePalette)
+        .on_submit(Message::SubmitPalette)
+        .padding(8);
+    let list = if matches.is_empty() {
+        column![input, text("No matches").size(12)]
+    } else {
+        matches
+            .into_iter()
+            .enumerate()
+            .fold(column![input], |list, (index, (label, message))| {
+                list.push(
+                    button(text(label).size(14))
+                        .on_press(Message::RunCommand(Box::new(message)))
+                        .style(if index == palette.selected {
+                            iced::theme::Button::Primary
+                        } else {
+                            iced::theme::Button::Text
+                        })
+                        .width(Length::Fill),
+                )
+            })
+    };
+    container(list.spacing(5))
+        .padding(10)
+        .max_width(600)
+        .style(iced::theme::Container::Box)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consecutive_characters_score_higher() {
+        let run = fuzzy_score("ab", "abxx").unwrap();
+        let gap = fuzzy_score("ab", "axbx").unwrap();
+        assert!(run > gap, "{run} should beat {gap}");
+    }
+
+    #[test]
+    fn word_starts_score_higher() {
+        let starts = fuzzy_score("nn", "New Note").unwrap();
+        let middles = fuzzy_score("nn", "xnxnxxxx").unwrap();
+        assert!(starts > middles, "{starts} should beat {middles}");
+    }
+
+    #[test]
+    fn characters_missing_or_out_of_order_do_not_match() {
+        assert_eq!(fuzzy_score("xyz", "New Note"), None);
+        assert_eq!(fuzzy_score("ba", "ab"), None);
+        assert_eq!(fuzzy_score("notes", "Note"), None);
+    }
+
+    #[test]
+    fn shorter_candidates_win_ties() {
+        let short = fuzzy_score("note", "Note").unwrap();
+        let long = fuzzy_score("note", "Notes").unwrap();
+        assert!(short > long, "{short} should beat {long}");
+    }
+
+    #[test]
+    fn an_empty_query_keeps_every_entry_in_order() {
+        assert_eq!(fuzzy_score("", "Anything at all"), Some(0));
+        let entries = vec![
+            ("Toggle Theme".to_string(), Message::ToggleTheme),
+            ("Go".to_string(), Message::ToggleTheme),
+        ];
+        let labels: Vec<String> = rank("  ", entries)
+            .into_iter()
+            .map(|(label, _)| label)
+            .collect();
+        assert_eq!(labels, ["Toggle Theme", "Go"]);
+    }
+}
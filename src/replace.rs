@@ -0,0 +1,202 @@
+//! Find and replace within a single note's content.
+
+use iced::theme::Button as ButtonTheme;
+use iced::widget::{button, checkbox, row, text, text_input};
+use iced::Element;
+use std::ops::Range;
+
+use crate::message::Message;
+
+/// The find/replace bar while it's open.
+#[derive(Debug, Default)]
+pub struct FindBar {
+    pub find: String,
+    pub replace: String,
+    pub case_sensitive: bool,
+    /// Byte offset in the content where Replace looks for the next match,
+    /// so repeated presses walk through the note.
+    pub next: usize,
+}
+
+impl FindBar {
+    /// `content` with the first match at or after `next` replaced, wrapping
+    /// round if need be, and the offset to look from after it.
+    pub fn replace_next(&self, content: &str) -> Option<(String, usize)> {
+        let found = find_from(content, &self.find, self.next, self.case_sensitive)?;
+        let mut replaced = content.to_string();
+        replaced.replace_range(found.clone(), &self.replace);
+        Some((replaced, found.start + self.replace.len()))
+    }
+}
+
+pub fn find_input_id() -> text_input::Id {
+    text_input::Id::new("find")
+}
+
+/// Length in bytes of `needle` if `haystack` starts with it. Without
+/// `case_sensitive`, letters are compared by their lowercase forms, so the
+/// match may differ in byte length from `needle`.
+fn match_len(haystack: &str, needle: &str, case_sensitive: bool) -> Option<usize> {
+    if case_sensitive {
+        return haystack.starts_with(needle).then_some(needle.len());
+    }
+    let mut chars = haystack.char_indices();
+    for expected in needle.chars() {
+        let (_, found) = chars.next()?;
+        if !found.to_lowercase().eq(expected.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(chars.next().map_or(haystack.len(), |(end, _)| end))
+}
+
+/// The byte range of the first match of `needle` in `content` starting at
+/// or after byte `from`, wrapping round to the start if there's none
+/// after it. An empty `needle` matches nothing.
+pub fn find_from(
+    content: &str,
+    needle: &str,
+    from: usize,
+    case_sensitive: bool,
+) -> Option<Range<usize>> {
+    if needle.is_empty() {
+        return None;
+    }
+    let find_in = |start: usize, end: usize| {
+        content[start..]
+            .char_indices()
+            .map(|(at, _)| start + at)
+            .take_while(|&at| at < end)
+            .find_map(|at| {
+                match_len(&content[at..], needle, case_sensitive).map(|len| at..at + len)
+            })
+    };
+    // The content may have been edited since `from` was worked out.
+    let mut from = from.min(content.len());
+    while !content.is_char_boundary(from) {
+        from -= 1;
+    }
+    find_in(from, content.len()).or_else(|| find_in(0, from))
+}
+
+/// `content` with every match of `needle` replaced by `replacement`, and
+/// how many there were. Matches don't overlap and replacements aren't
+/// searched again.
+pub fn replace_all(
+    content: &str,
+    needle: &str,
+    replacement: &str,
+    case_sensitive: bool,
+) -> (String, usize) {
+    let mut replaced = String::with_capacity(content.len());
+    let mut count = 0;
+    let mut rest = 0;
+    while let Some(found) =
+        find_from(content, needle, rest, case_sensitive).filter(|found| found.start >= rest)
+    {
+        replaced.push_str(&content[rest..found.start]);
+        replaced.push_str(replacement);
+        rest = found.end;
+        count += 1;
+    }
+    replaced.push_str(&content[rest..]);
+    (replaced, count)
+}
+
+/// The find and replace fields over a note; replacing is offered only
+/// when the note isn't `locked` and there's something to find.
+pub fn view(bar: &FindBar, locked: bool) -> Element<'_, Message> {
+    let editable = !locked && !bar.find.is_empty();
+    row![
+        text_input("Find", &bar.find)
+            .id(find_input_id())
+            .on_input(Message::UpdateFind)
+            .on_submit(Message::Replace)
+            .padding(5)
+            .width(180),
+        text_input("Replace with", &bar.replace)
+            .on_input(Message::UpdateReplace)
+            .on_submit(Message::Replace)
+            .padding(5)
+            .width(180),
+        checkbox("Match case", bar.case_sensitive).on_toggle(Message::ToggleMatchCase),
+        button(text("Replace").size(12)).on_press_maybe(editable.then_some(Message::Replace)),
+        button(text("Replace All").size(12))
+            .on_press_maybe(editable.then_some(Message::ReplaceAll)),
+        button(text("✕").size(12))
+            .on_press(Message::ToggleFindBar)
+            .style(ButtonTheme::Text),
+    ]
+    .spacing(10)
+    .align_items(iced::Alignment::Center)
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_from_an_offset_and_wraps_around() {
+        assert_eq!(find_from("cat hat cat", "cat", 1, true), Some(8..11));
+        assert_eq!(find_from("cat hat cat", "cat", 9, true), Some(0..3));
+        assert_eq!(find_from("cat hat cat", "dog", 0, true), None);
+    }
+
+    #[test]
+    fn ignores_case_unless_asked_not_to() {
+        assert_eq!(find_from("Hello World", "world", 0, false), Some(6..11));
+        assert_eq!(find_from("Hello World", "world", 0, true), None);
+        assert_eq!(find_from("ÉCOLE école", "éCOLE", 2, false), Some(7..13));
+        assert_eq!(
+            replace_all("Cat cat CAT", "cat", "dog", false),
+            ("dog dog dog".to_string(), 3)
+        );
+        assert_eq!(
+            replace_all("Cat cat CAT", "cat", "dog", true),
+            ("Cat dog CAT".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn replacements_are_not_searched_again() {
+        assert_eq!(
+            replace_all("a b a", "a", "aaa", true),
+            ("aaa b aaa".to_string(), 2)
+        );
+        assert_eq!(
+            replace_all("one two", "one", "one and a half", true),
+            ("one and a half two".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn replace_next_walks_through_the_matches() {
+        let mut bar = FindBar {
+            find: "cat".to_string(),
+            replace: "dog".to_string(),
+            ..FindBar::default()
+        };
+        let (content, next) = bar.replace_next("cat hat cat").unwrap();
+        assert_eq!((content.as_str(), next), ("dog hat cat", 3));
+        bar.next = next;
+        let (content, next) = bar.replace_next(&content).unwrap();
+        assert_eq!((content.as_str(), next), ("dog hat dog", 11));
+        bar.next = next;
+        assert_eq!(bar.replace_next(&content), None);
+    }
+
+    #[test]
+    fn an_empty_query_matches_nothing() {
+        assert_eq!(find_from("anything", "", 0, false), None);
+        assert_eq!(
+            replace_all("anything", "", "x", false),
+            ("anything".to_string(), 0)
+        );
+    }
+
+    #[test]
+    fn an_offset_inside_a_character_is_moved_back_to_its_start() {
+        assert_eq!(find_from("é é", "é", 1, true), Some(0..2));
+    }
+}
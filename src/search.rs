@@ -0,0 +1,291 @@
+//! In-memory inverted index over note titles and contents.
+//!
+//! Scanning every note's content with `contains` on each keystroke gets
+//! sluggish with thousands of notes; the index maps lowercased words to the
+//! notes containing them so most of that work is done once per edit instead.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+
+use crate::note::Note;
+
+/// What a note looked like when it was last indexed. Anything that changes
+/// a note's text also bumps `updated_at` or flips `encrypted`.
+#[derive(Debug, Clone, PartialEq)]
+struct Indexed {
+    updated_at: DateTime<Utc>,
+    encrypted: bool,
+    title_len: usize,
+    tokens: HashSet<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    postings: BTreeMap<String, HashSet<String>>,
+    notes: HashMap<String, Indexed>,
+}
+
+/// Splits `text` into lowercased alphanumeric words.
+pub fn tokens(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+impl SearchIndex {
+    /// Brings the index up to date with `notes`, re-tokenizing only notes
+    /// that changed since the last call and dropping ones that are gone.
+    pub fn sync(&mut self, notes: &HashMap<String, Note>) {
+        let stale: Vec<String> = self
+            .notes
+            .keys()
+            .filter(|id| !notes.contains_key(*id))
+            .cloned()
+            .collect();
+        for id in stale {
+            self.remove(&id);
+        }
+
+        for note in notes.values() {
+            let unchanged = self.notes.get(&note.id).is_some_and(|indexed| {
+                indexed.updated_at == note.updated_at
+                    && indexed.encrypted == note.encrypted
+                    && indexed.title_len == note.title.len()
+            });
+            if !unchanged {
+                self.insert(note);
+            }
+        }
+    }
+
+    fn insert(&mut self, note: &Note) {
+        self.remove(&note.id);
+        let mut words: HashSet<String> = tokens(&note.title).collect();
+        // Locked notes only expose their title; the content is ciphertext.
+        if !note.encrypted {
+            words.extend(tokens(&note.content));
+        }
+        for word in &words {
+            self.postings
+                .entry(word.clone())
+                .or_default()
+                .insert(note.id.clone());
+        }
+        self.notes.insert(
+            note.id.clone(),
+            Indexed {
+                updated_at: note.updated_at,
+                encrypted: note.encrypted,
+                title_len: note.title.len(),
+                tokens: words,
+            },
+        );
+    }
+
+    fn remove(&mut self, id: &str) {
+        let Some(indexed) = self.notes.remove(id) else {
+            return;
+        };
+        for word in indexed.tokens {
+            if let Some(ids) = self.postings.get_mut(&word) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.postings.remove(&word);
+                }
+            }
+        }
+    }
+
+    /// Notes sharing words with the note `id`, as `(id, score)` pairs best
+    /// first. The score is the Jaccard index of the two notes' word sets;
+    /// notes scoring below `threshold` are left out.
+    ///
+    /// Overlaps are counted through the postings of `id`'s own words, so
+    /// notes with nothing in common are never looked at.
+    pub fn similar(&self, id: &str, threshold: f32) -> Vec<(String, f32)> {
+        let Some(target) = self.notes.get(id) else {
+            return Vec::new();
+        };
+        let mut shared: HashMap<&str, usize> = HashMap::new();
+        for word in &target.tokens {
+            for other in self.postings.get(word).into_iter().flatten() {
+                if other != id {
+                    *shared.entry(other.as_str()).or_default() += 1;
+                }
+            }
+        }
+
+        let mut similar: Vec<(String, f32)> = shared
+            .into_iter()
+            .filter_map(|(other, common)| {
+                let union = target.tokens.len() + self.notes[other].tokens.len() - common;
+                let score = common as f32 / union as f32;
+                (score >= threshold).then(|| (other.to_string(), score))
+            })
+            .collect();
+        similar.sort_by(|(a, a_score), (b, b_score)| {
+            b_score.total_cmp(a_score).then_with(|| a.cmp(b))
+        });
+        similar
+    }
+
+    /// Ids of notes containing every word of `query`, or `None` when the
+    /// query has no words to look up (e.g. it's only punctuation) and the
+    /// caller should fall back to a plain substring scan.
+    ///
+    /// Words before the last are matched exactly. The last word is usually
+    /// still being typed, so it matches any indexed word starting with it,
+    /// found with a range over the sorted words. Unlike the substring scan,
+    /// words only match from their start, and a query of several words
+    /// matches them anywhere in the note; callers wanting them together as
+    /// a phrase check the notes returned.
+    pub fn search(&self, query: &str) -> Option<HashSet<String>> {
+        let words: Vec<String> = tokens(query).collect();
+        let (last, complete) = words.split_last()?;
+        let partial = !query.ends_with(|c: char| !c.is_alphanumeric());
+
+        let mut matches: Option<HashSet<String>> = None;
+        for word in complete {
+            let ids = self.postings.get(word).cloned().unwrap_or_default();
+            matches = Some(intersect(matches, ids));
+        }
+
+        let ids = if partial {
+            self.postings
+                .range(last.clone()..)
+                .take_while(|(indexed, _)| indexed.starts_with(last.as_str()))
+                .flat_map(|(_, ids)| ids.iter().cloned())
+                .collect()
+        } else {
+            self.postings.get(last).cloned().unwrap_or_default()
+        };
+        Some(intersect(matches, ids))
+    }
+}
+
+fn intersect(matches: Option<HashSet<String>>, ids: HashSet<String>) -> HashSet<String> {
+    match matches {
+        Some(matches) => matches.intersection(&ids).cloned().collect(),
+        None => ids,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn index(notes: &[(&str, &str)]) -> (HashMap<String, Note>, SearchIndex) {
+        let notes: HashMap<String, Note> = notes
+            .iter()
+            .map(|&(title, content)| {
+                let note = Note::new(title, content.to_string());
+                (note.id.clone(), note)
+            })
+            .collect();
+        let mut index = SearchIndex::default();
+        index.sync(&notes);
+        (notes, index)
+    }
+
+    fn titles(notes: &HashMap<String, Note>, ids: Option<HashSet<String>>) -> Vec<&str> {
+        let mut titles: Vec<&str> = ids
+            .unwrap_or_default()
+            .iter()
+            .map(|id| notes[id].title.as_str())
+            .collect();
+        titles.sort();
+        titles
+    }
+
+    #[test]
+    fn the_last_word_matches_as_a_prefix() {
+        let (notes, index) = index(&[
+            ("Groceries", "milk and eggs"),
+            ("Footnote", "see below"),
+            ("Notebook", "paper"),
+        ]);
+        assert_eq!(titles(&notes, index.search("note")), ["Notebook"]);
+        assert_eq!(titles(&notes, index.search("MIL")), ["Groceries"]);
+        assert_eq!(titles(&notes, index.search("note ")), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn earlier_words_match_exactly() {
+        let (notes, index) = index(&[("A", "milk and eggs"), ("B", "milky way and stars")]);
+        assert_eq!(titles(&notes, index.search("milk and")), ["A"]);
+        assert_eq!(titles(&notes, index.search("and st")), ["B"]);
+    }
+
+    #[test]
+    fn a_query_without_words_falls_back() {
+        let (_, index) = index(&[("A", "text")]);
+        assert_eq!(index.search("?!"), None);
+    }
+
+    #[test]
+    fn edits_and_deletions_are_picked_up() {
+        let (mut notes, mut index) = index(&[("A", "apple")]);
+        let id = notes.keys().next().unwrap().clone();
+        let note = notes.get_mut(&id).unwrap();
+        note.content = "banana".to_string();
+        note.updated_at += chrono::Duration::seconds(1);
+        index.sync(&notes);
+        assert_eq!(titles(&notes, index.search("apple")), Vec::<&str>::new());
+        assert_eq!(titles(&notes, index.search("banana")), ["A"]);
+
+        notes.clear();
+        index.sync(&notes);
+        assert!(index.search("banana").unwrap().is_empty());
+    }
+
+    /// Times the index against the plain scan it replaced. Run with
+    /// `cargo test --release -- --ignored --nocapture indexed_search`.
+    #[test]
+    #[ignore]
+    fn indexed_search_beats_a_scan() {
+        const WORDS: [&str; 8] = [
+            "alpha", "bravo", "charlie", "delta", "echo", "fox", "golf", "hotel",
+        ];
+        let notes: HashMap<String, Note> = (0..5_000)
+            .map(|i| {
+                let content = (0..200)
+                    .map(|j| format!("{}{}", WORDS[(i * 7 + j) % WORDS.len()], j % 50))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let note = Note::new(format!("Note {}", i), content);
+                (note.id.clone(), note)
+            })
+            .collect();
+        let mut index = SearchIndex::default();
+        index.sync(&notes);
+        // Each keystroke of a query being typed.
+        let queries = ["d", "de", "del", "delt", "delta", "delta4", "delta42"];
+
+        let started = Instant::now();
+        let scanned: Vec<usize> = queries
+            .iter()
+            .map(|query| {
+                notes
+                    .values()
+                    .filter(|note| note.content.to_lowercase().contains(query))
+                    .count()
+            })
+            .collect();
+        let scan = started.elapsed();
+
+        let started = Instant::now();
+        let indexed: Vec<usize> = queries
+            .iter()
+            .map(|query| index.search(query).map_or(0, |ids| ids.len()))
+            .collect();
+        let lookup = started.elapsed();
+
+        println!("scan: {:?}, index: {:?}", scan, lookup);
+        // Every synthetic word is a name and a number, so prefixes of one
+        // find the same notes as substrings do.
+        assert_eq!(scanned, indexed);
+        assert!(lookup < scan);
+    }
+}
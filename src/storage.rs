@@ -0,0 +1,782 @@
+//! Reading and writing the notes file, its backups, and the preferences file.
+
+use chrono::Utc;
+use iced::window;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::app::{
+    SortMode, DEFAULT_AUTOSAVE_INTERVAL_SECS, DEFAULT_FONT_SIZE, DEFAULT_SIMILARITY_THRESHOLD,
+    DEFAULT_SPLIT_RATIO,
+};
+use crate::note::{note_to_markdown, Note, NoteColor};
+use crate::style::SidebarStyle;
+
+/// File name of the notes file, used on startup and by auto-save.
+pub const NOTES_FILE: &str = "notes.json";
+
+const PREFERENCES_FILE: &str = "settings.json";
+
+/// Schema version written to the notes file; see `migrate`.
+const NOTES_FILE_VERSION: u32 = 2;
+
+/// How many copies of `notes.json` to keep in the `backups` directory.
+const MAX_BACKUPS: usize = 10;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub width: u32,
+    pub height: u32,
+    pub position: Option<(i32, i32)>,
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        let size = window::Settings::default().size;
+        Self {
+            width: size.width as u32,
+            height: size.height as u32,
+            position: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub sort_mode: SortMode,
+    pub dark_mode: bool,
+    pub reuse_blank_notes: bool,
+    pub split_ratio: f32,
+    pub window: Option<WindowGeometry>,
+    pub font_size: u16,
+    /// Id of the note that was open when the app last ran.
+    pub current_note: Option<String>,
+    /// Ids of the notes open in editor tabs, left to right.
+    pub open_tabs: Vec<String>,
+    /// Whether sidebar entries show a line of content under the title.
+    pub show_snippets: bool,
+    /// How each note's color shows in the sidebar.
+    pub sidebar_style: SidebarStyle,
+    /// Seconds of quiet before edits are saved, from 0 (off) to 300.
+    pub autosave_interval_secs: u64,
+    /// Lowest word overlap, from 0 to 1, for "Find Similar" to list a note.
+    pub similarity_threshold: f32,
+    /// The app version the "What's new" panel was last dismissed in.
+    pub last_seen_version: Option<String>,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            sort_mode: SortMode::default(),
+            dark_mode: false,
+            reuse_blank_notes: true,
+            split_ratio: DEFAULT_SPLIT_RATIO,
+            window: None,
+            font_size: DEFAULT_FONT_SIZE,
+            current_note: None,
+            open_tabs: Vec::new(),
+            show_snippets: true,
+            sidebar_style: SidebarStyle::default(),
+            autosave_interval_secs: DEFAULT_AUTOSAVE_INTERVAL_SECS,
+            similarity_threshold: DEFAULT_SIMILARITY_THRESHOLD,
+            last_seen_version: None,
+        }
+    }
+}
+
+/// On-disk layout of the notes file. Notes are listed in id order, so a
+/// save only rewrites the lines of notes that changed.
+#[derive(Debug, Serialize, Deserialize)]
+struct NotesFile<N> {
+    version: u32,
+    notes: N,
+}
+
+/// Directory holding the notes file, its backups and the preferences.
+/// Falls back to the working directory on platforms without one.
+fn data_dir() -> PathBuf {
+    directories::ProjectDirs::from("in", "thevenice", "Multi-Notes")
+        .map(|dirs| dirs.data_dir().to_path_buf())
+        .unwrap_or_default()
+}
+
+/// Where notes are loaded from on startup and auto-saved to, unless a path
+/// is given on the command line.
+pub fn storage_path() -> PathBuf {
+    data_dir().join(NOTES_FILE)
+}
+
+/// Where the notes are copied before an import replaces them, so the
+/// import can be undone.
+pub fn import_backup_path() -> PathBuf {
+    data_dir().join("import-backup.json")
+}
+
+/// Reads the notes at `storage_path()`. Earlier versions kept `notes.json`
+/// in the working directory, so that is tried when nothing is there yet;
+/// the next save then moves the notes to the new location.
+pub fn load_notes() -> Result<HashMap<String, Note>, Box<dyn std::error::Error>> {
+    let path = storage_path();
+    match read_notes_file(&path) {
+        Err(e) if is_not_found(e.as_ref()) => {
+            let legacy = Path::new(NOTES_FILE);
+            read_notes_file(legacy).map_err(|e| quarantine_if_corrupt(legacy, e))
+        }
+        result => result.map_err(|e| quarantine_if_corrupt(&path, e)),
+    }
+}
+
+/// Reads the notes at `path`, a location the user chose explicitly.
+pub fn load_notes_from(path: &Path) -> Result<HashMap<String, Note>, Box<dyn std::error::Error>> {
+    read_notes_file(path).map_err(|e| quarantine_if_corrupt(path, e))
+}
+
+/// A notes file that couldn't be parsed and was moved out of the way.
+#[derive(Debug)]
+pub struct Quarantined {
+    pub moved_to: PathBuf,
+}
+
+impl fmt::Display for Quarantined {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the file is damaged, so it was moved to {} to keep it from being overwritten",
+            self.moved_to.display()
+        )
+    }
+}
+
+impl std::error::Error for Quarantined {}
+
+/// If `error` means the file at `path` isn't valid JSON, renames the file to
+/// `notes.corrupt-<timestamp>.json` beside it so auto-save can't clobber
+/// whatever is left of it. Other errors, or a failed rename, pass through.
+fn quarantine_if_corrupt(
+    path: &Path,
+    error: Box<dyn std::error::Error>,
+) -> Box<dyn std::error::Error> {
+    if !error.is::<serde_json::Error>() {
+        return error;
+    }
+    let timestamp = Utc::now().format("%Y%m%d-%H%M%S");
+    let moved_to = path.with_file_name(format!("notes.corrupt-{}.json", timestamp));
+    match fs::rename(path, &moved_to) {
+        Ok(()) => Box::new(Quarantined { moved_to }),
+        Err(_) => error,
+    }
+}
+
+pub fn read_notes_file(path: &Path) -> Result<HashMap<String, Note>, Box<dyn std::error::Error>> {
+    read_notes_file_repairing_ids(path).map(|(notes, _)| notes)
+}
+
+/// Like `read_notes_file`, also saying how many notes were given a fresh
+/// id; see `notes_by_id`.
+pub fn read_notes_file_repairing_ids(
+    path: &Path,
+) -> Result<(HashMap<String, Note>, usize), Box<dyn std::error::Error>> {
+    let json = fs::read_to_string(path)?;
+    migrate(serde_json::from_str(&json)?)
+}
+
+/// Writes `notes` to `path` in the current file format.
+pub fn write_notes_file(
+    path: &Path,
+    notes: HashMap<String, Note>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    write_notes(path, notes.values())
+}
+
+/// Like `write_notes_file`, for notes borrowed from wherever they're kept.
+pub fn write_notes<'a>(
+    path: &Path,
+    notes: impl IntoIterator<Item = &'a Note>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut notes: Vec<&Note> = notes.into_iter().collect();
+    notes.sort_by(|a, b| a.id.cmp(&b.id));
+    let file = NotesFile {
+        version: NOTES_FILE_VERSION,
+        notes,
+    };
+    let json = serde_json::to_string_pretty(&file)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Write next to the real file and rename over it, so a crash
+    // mid-write never leaves a truncated notes file behind.
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Identifies the contents of the file at `path`, to notice when something
+/// other than this app rewrites it. `None` when there's nothing to read.
+pub fn fingerprint(path: &Path) -> Option<u64> {
+    let bytes = fs::read(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// A save was held back because the notes file no longer matches what was
+/// last loaded from or written to it.
+#[derive(Debug)]
+pub struct ChangedOnDisk;
+
+impl fmt::Display for ChangedOnDisk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the notes file was changed on disk by something else")
+    }
+}
+
+impl std::error::Error for ChangedOnDisk {}
+
+/// The lock file beside the notes file at `path`, e.g. `notes.json.lock`,
+/// holding the process id of the instance allowed to write it.
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+/// Claims the notes file at `path` for this process. Returns the id of
+/// another running process already holding it, or `None` once the lock is
+/// ours. A lock left behind by a process that is no longer running is
+/// taken over.
+pub fn acquire_lock(path: &Path) -> io::Result<Option<u32>> {
+    let lock = lock_path(path);
+    if let Some(dir) = lock.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let ours = std::process::id();
+    match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&lock)
+    {
+        Ok(mut file) => {
+            write!(file, "{}", ours)?;
+            return Ok(None);
+        }
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e),
+    }
+    let holder = fs::read_to_string(&lock)
+        .ok()
+        .and_then(|pid| pid.trim().parse::<u32>().ok());
+    match holder {
+        Some(pid) if pid != ours && process_alive(pid) => Ok(Some(pid)),
+        _ => {
+            take_lock(path)?;
+            Ok(None)
+        }
+    }
+}
+
+/// Claims the notes file at `path` for this process regardless of who
+/// held it.
+pub fn take_lock(path: &Path) -> io::Result<()> {
+    fs::write(lock_path(path), std::process::id().to_string())
+}
+
+/// Gives up this process's claim on the notes file at `path`, leaving a
+/// lock held by anyone else alone.
+pub fn release_lock(path: &Path) -> io::Result<()> {
+    let lock = lock_path(path);
+    let holder = fs::read_to_string(&lock)?;
+    if holder.trim() == std::process::id().to_string() {
+        fs::remove_file(lock)?;
+    }
+    Ok(())
+}
+
+/// Whether a process with id `pid` is running, asked of `kill -0`.
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .arg("-0")
+        .arg(pid.to_string())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Without a portable way to ask, a lock is assumed live; the banner still
+/// lets the user take it over.
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+/// Replaces the one entry for `note` in the notes file at `path`, leaving
+/// every other note in the file as it is on disk.
+pub fn write_note_into_file(path: &Path, note: Note) -> Result<(), Box<dyn std::error::Error>> {
+    let mut notes = match read_notes_file(path) {
+        Ok(notes) => notes,
+        Err(e) if is_not_found(e.as_ref()) => HashMap::new(),
+        Err(e) => return Err(e),
+    };
+    notes.insert(note.id.clone(), note);
+    write_notes_file(path, notes)
+}
+
+/// Reads any historical notes file layout into notes keyed by id, along
+/// with how many notes `notes_by_id` had to give a fresh id.
+///
+/// - v0 has no `version` field: the file is the bare id → note map. The
+///   earliest prototype files in that shape keyed notes by their label and
+///   used `label`/`background_color` instead of `id`/`title`/`color`. A
+///   bare list of notes is taken as well.
+/// - v1 wraps the id → note map as `{ version, notes }`.
+/// - v2 is the current `{ version, notes }` with `notes` a list sorted by id.
+fn migrate(
+    raw: serde_json::Value,
+) -> Result<(HashMap<String, Note>, usize), Box<dyn std::error::Error>> {
+    let unkeyed = |list: Vec<Note>| list.into_iter().map(|note| (None, note));
+    match raw.get("version").and_then(serde_json::Value::as_u64) {
+        None if raw.is_array() => Ok(notes_by_id(unkeyed(serde_json::from_value(raw)?))),
+        None => migrate_v0(raw),
+        Some(1) => {
+            let file = serde_json::from_value::<NotesFile<HashMap<String, Note>>>(raw)?;
+            Ok(notes_by_id(
+                file.notes.into_iter().map(|(key, note)| (Some(key), note)),
+            ))
+        }
+        Some(2) => Ok(notes_by_id(unkeyed(
+            serde_json::from_value::<NotesFile<Vec<Note>>>(raw)?.notes,
+        ))),
+        Some(version) => Err(format!(
+            "notes file version {} is newer than this app supports ({})",
+            version, NOTES_FILE_VERSION
+        )
+        .into()),
+    }
+}
+
+/// Keys notes read from a file by id, along with the key the file stored
+/// each under, if any. A note whose id is empty, disagrees with its key or
+/// repeats an earlier note's gets a fresh id rather than being dropped or
+/// replacing another, and counts towards the number returned.
+fn notes_by_id(
+    entries: impl IntoIterator<Item = (Option<String>, Note)>,
+) -> (HashMap<String, Note>, usize) {
+    let mut notes = HashMap::new();
+    let mut repaired = 0;
+    for (key, mut note) in entries {
+        if note.id.trim().is_empty()
+            || key.is_some_and(|key| key != note.id)
+            || notes.contains_key(&note.id)
+        {
+            note.id = uuid::Uuid::new_v4().to_string();
+            repaired += 1;
+        }
+        notes.insert(note.id.clone(), note);
+    }
+    (notes, repaired)
+}
+
+fn migrate_v0(
+    raw: serde_json::Value,
+) -> Result<(HashMap<String, Note>, usize), Box<dyn std::error::Error>> {
+    let entries: serde_json::Map<String, serde_json::Value> = serde_json::from_value(raw)?;
+    let mut notes = Vec::with_capacity(entries.len());
+    for (key, value) in entries {
+        // Prototype notes were keyed by their label and never had an id.
+        if value.get("id").is_none() && value.get("label").is_some() {
+            notes.push((None, migrate_prototype_note(&value)));
+        } else {
+            notes.push((Some(key), serde_json::from_value::<Note>(value)?));
+        }
+    }
+    Ok(notes_by_id(notes))
+}
+
+fn migrate_prototype_note(value: &serde_json::Value) -> Note {
+    let field = |name| value.get(name).and_then(serde_json::Value::as_str);
+    let mut note = Note::new(
+        field("label").unwrap_or_default(),
+        field("content").unwrap_or_default().to_string(),
+    );
+    note.color = match field("background_color").map(str::to_lowercase).as_deref() {
+        Some("red") => NoteColor::Red.to_rgb(),
+        Some("green") => NoteColor::Green.to_rgb(),
+        Some("blue") => NoteColor::Blue.to_rgb(),
+        Some("orange") => NoteColor::Orange.to_rgb(),
+        Some("white") => [1.0, 1.0, 1.0],
+        _ => NoteColor::Yellow.to_rgb(),
+    };
+    note
+}
+
+pub fn is_not_found(error: &(dyn std::error::Error + 'static)) -> bool {
+    error
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound)
+}
+
+/// Packs `notes`, less any in the trash, into a zip archive holding one
+/// `<title>.md` file per note and a `manifest.json` listing every note's
+/// metadata and file name.
+pub fn notes_archive(notes: &HashMap<String, Note>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut notes: Vec<&Note> = notes
+        .values()
+        .filter(|note| note.trashed_at.is_none())
+        .collect();
+    notes.sort_by_key(|note| (note.title.to_lowercase(), &note.id));
+
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut archive = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+    let mut used = HashSet::new();
+    let mut manifest = Vec::new();
+    for note in notes {
+        let stem = sanitize_file_name(&note.title);
+        let mut file = format!("{}.md", stem);
+        // Zip tools on macOS and Windows extract case-insensitively.
+        if !used.insert(file.to_lowercase()) {
+            file = format!("{} ({}).md", stem, note.id);
+            used.insert(file.to_lowercase());
+        }
+        archive.start_file(file.as_str(), options)?;
+        archive.write_all(note_to_markdown(note).as_bytes())?;
+
+        let mut entry = serde_json::to_value(note)?;
+        if let Some(entry) = entry.as_object_mut() {
+            entry.remove("content");
+            entry.insert("file".to_string(), file.into());
+        }
+        manifest.push(entry);
+    }
+    archive.start_file("manifest.json", options)?;
+    archive.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    Ok(archive.finish()?.into_inner())
+}
+
+/// Replaces characters that aren't allowed (or are awkward) in file names
+/// on common filesystems, falling back to "Untitled" for empty results.
+fn sanitize_file_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    // Leading dots would hide the file; trailing dots and spaces are
+    // dropped by Windows.
+    let name = name
+        .trim()
+        .trim_start_matches('.')
+        .trim_end_matches(['.', ' ']);
+    if name.is_empty() {
+        "Untitled".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Copies the file at `path` into a `backups/` directory beside it and
+/// prunes all but the newest `MAX_BACKUPS` copies. Only meant for the
+/// app's own notes file, not for files the user exports.
+pub fn rotate_backups(path: &Path) -> io::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let dir = path.with_file_name("backups");
+    fs::create_dir_all(&dir)?;
+    let timestamp = Utc::now().format("%Y%m%d-%H%M%S%.3f");
+    fs::copy(path, dir.join(format!("notes-{}.json", timestamp)))?;
+
+    let mut backups: Vec<_> = fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("notes-") && name.ends_with(".json"))
+        })
+        .collect();
+    // Timestamps sort lexicographically, so the oldest come first.
+    backups.sort();
+    let excess = backups.len().saturating_sub(MAX_BACKUPS);
+    for path in &backups[..excess] {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Whether `path` names a `.txt` or `.md` file, by its extension.
+pub fn is_text_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("txt") || extension.eq_ignore_ascii_case("md")
+        })
+}
+
+/// Reads the text file at `path` as a note titled after the file name.
+/// Fails with `InvalidData` if it isn't valid UTF-8.
+pub fn read_text_note(path: &Path) -> io::Result<Note> {
+    let content = fs::read_to_string(path)?;
+    let title = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Ok(Note::new(title, content))
+}
+
+/// Reads every `.txt`/`.md` file directly inside `dir` as a note, returning
+/// the notes and how many files were skipped for not being valid UTF-8.
+pub fn read_text_notes(dir: &Path) -> io::Result<(Vec<Note>, usize)> {
+    let mut notes = Vec::new();
+    let mut skipped = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() || !is_text_file(&path) {
+            continue;
+        }
+        match read_text_note(&path) {
+            Ok(note) => notes.push(note),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => skipped += 1,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok((notes, skipped))
+}
+
+pub fn load_preferences() -> Preferences {
+    // Like `load_notes`, accept a settings file left in the working
+    // directory by earlier versions.
+    fs::read_to_string(data_dir().join(PREFERENCES_FILE))
+        .or_else(|_| fs::read_to_string(PREFERENCES_FILE))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_preferences(preferences: &Preferences) -> Result<(), Box<dyn std::error::Error>> {
+    let json = serde_json::to_string(preferences)?;
+    let dir = data_dir();
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(PREFERENCES_FILE), json)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// A note with every field set away from its default.
+    fn full_note() -> Note {
+        Note {
+            id: "a1".to_string(),
+            title: "Groceries ✓".to_string(),
+            content: "- [ ] milk\n- [x] café\n\ttabs and \"quotes\"".to_string(),
+            color: [0.1, 0.25, 0.9],
+            created_at: Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap(),
+            updated_at: Utc.with_ymd_and_hms(2024, 6, 7, 8, 9, 10).unwrap(),
+            pinned: true,
+            tags: vec!["home".to_string(), "errands".to_string()],
+            notebook: Some("Personal".to_string()),
+            encrypted: true,
+            order: -3,
+            favorite: true,
+            title_is_manual: true,
+            due: Some(Utc.with_ymd_and_hms(2024, 12, 31, 23, 59, 0).unwrap()),
+            monospace: true,
+            locked: true,
+            revision: 42,
+            trashed_at: Some(Utc.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap()),
+        }
+    }
+
+    fn by_id(notes: impl IntoIterator<Item = Note>) -> HashMap<String, Note> {
+        notes
+            .into_iter()
+            .map(|note| (note.id.clone(), note))
+            .collect()
+    }
+
+    #[test]
+    fn notes_survive_a_write_and_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(NOTES_FILE);
+        let notes = by_id([full_note(), Note::new("Plain", String::new())]);
+
+        write_notes_file(&path, notes.clone()).unwrap();
+
+        assert_eq!(read_notes_file(&path).unwrap(), notes);
+    }
+
+    fn migrated(json: &str) -> HashMap<String, Note> {
+        migrate(serde_json::from_str(json).unwrap()).unwrap().0
+    }
+
+    #[test]
+    fn migrates_a_v0_map() {
+        let notes = migrated(
+            r#"{
+                "n1": { "id": "n1", "title": "First", "content": "one", "color": "Blue" },
+                "n2": { "id": "n2", "title": "Second", "content": "two", "color": [0.5, 0.5, 0.5] }
+            }"#,
+        );
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes["n1"].title, "First");
+        assert_eq!(notes["n1"].color, NoteColor::Blue.to_rgb());
+        assert_eq!(notes["n2"].content, "two");
+        assert_eq!(notes["n2"].color, [0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn a_v0_note_sharing_an_id_gets_a_fresh_one() {
+        let (notes, repaired) = migrate(serde_json::json!({
+            "n1": { "id": "n1", "title": "First", "content": "one", "color": "Blue" },
+            "n2": { "id": "n1", "title": "Second", "content": "two", "color": "Blue" }
+        }))
+        .unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(repaired, 1);
+        assert_eq!(notes["n1"].title, "First");
+        let second = notes.values().find(|note| note.title == "Second").unwrap();
+        assert_ne!(second.id, "n1");
+    }
+
+    #[test]
+    fn a_v0_note_keyed_under_another_id_gets_a_fresh_one() {
+        let (notes, repaired) = migrate(serde_json::json!({
+            "stale": { "id": "n1", "title": "Moved", "content": "", "color": "Red" },
+            "n2": { "id": "", "title": "Blank", "content": "", "color": "Red" }
+        }))
+        .unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(repaired, 2);
+        assert!(notes
+            .keys()
+            .all(|id| id != "n1" && id != "stale" && !id.is_empty()));
+        assert!(notes.iter().all(|(id, note)| *id == note.id));
+    }
+
+    #[test]
+    fn migrates_prototype_label_notes() {
+        let notes = migrated(
+            r#"{
+                "Shopping": { "label": "Shopping", "content": "eggs", "background_color": "Green" },
+                "Odd": { "label": "Odd", "content": "", "background_color": "magenta" }
+            }"#,
+        );
+        let mut notes: Vec<&Note> = notes.values().collect();
+        notes.sort_by_key(|note| &note.title);
+        assert_eq!(notes[0].title, "Odd");
+        assert_eq!(notes[0].color, NoteColor::Yellow.to_rgb());
+        assert_eq!(notes[1].title, "Shopping");
+        assert_eq!(notes[1].content, "eggs");
+        assert_eq!(notes[1].color, NoteColor::Green.to_rgb());
+        assert!(notes.iter().all(|note| !note.id.is_empty()));
+    }
+
+    #[test]
+    fn migrates_a_bare_array() {
+        let (notes, repaired) = migrate(serde_json::json!([
+            { "id": "n1", "title": "First", "content": "one", "color": "Red" },
+            { "id": "n1", "title": "Copy", "content": "same id", "color": "Red" }
+        ]))
+        .unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(repaired, 1);
+        assert_eq!(notes["n1"].title, "First");
+        let copy = notes.values().find(|note| note.title == "Copy").unwrap();
+        assert_ne!(copy.id, "n1");
+    }
+
+    #[test]
+    fn migrates_a_v1_map() {
+        let notes = migrated(
+            r#"{
+                "version": 1,
+                "notes": {
+                    "n1": { "id": "n1", "title": "First", "content": "one", "color": "Orange",
+                            "pinned": true, "tags": ["work"] }
+                }
+            }"#,
+        );
+        assert_eq!(notes.len(), 1);
+        assert!(notes["n1"].pinned);
+        assert_eq!(notes["n1"].tags, ["work"]);
+        assert_eq!(notes["n1"].color, NoteColor::Orange.to_rgb());
+    }
+
+    #[test]
+    fn refuses_a_newer_version() {
+        let raw = serde_json::json!({ "version": NOTES_FILE_VERSION + 1, "notes": [] });
+        assert!(migrate(raw).is_err());
+    }
+
+    #[test]
+    fn a_failed_write_leaves_the_old_file_intact() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(NOTES_FILE);
+        let old = by_id([Note::new("Old", "kept".to_string())]);
+        write_notes_file(&path, old.clone()).unwrap();
+
+        // A directory where the temporary file should go makes the write
+        // fail before anything could replace the notes file.
+        fs::create_dir(path.with_extension("json.tmp")).unwrap();
+        let new = by_id([Note::new("New", "lost".to_string())]);
+        assert!(write_notes_file(&path, new).is_err());
+
+        assert_eq!(read_notes_file(&path).unwrap(), old);
+    }
+
+    #[test]
+    fn notes_load_back_from_a_chosen_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("elsewhere").join("my-notes.json");
+        let notes = by_id([Note::new("Travel", "passport".to_string())]);
+
+        write_notes_file(&path, notes.clone()).unwrap();
+
+        assert_eq!(load_notes_from(&path).unwrap(), notes);
+        assert!(fingerprint(&path).is_some());
+    }
+
+    #[test]
+    fn a_missing_notes_file_is_reported_as_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let error = load_notes_from(&dir.path().join(NOTES_FILE)).unwrap_err();
+        assert!(is_not_found(error.as_ref()));
+    }
+
+    #[test]
+    fn a_corrupt_notes_file_is_moved_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(NOTES_FILE);
+        fs::write(&path, "{ \"version\": 2, \"notes\": [").unwrap();
+
+        let error = load_notes_from(&path).unwrap_err();
+        let quarantined = error.downcast_ref::<Quarantined>().unwrap();
+        let name = quarantined.moved_to.file_name().unwrap().to_string_lossy();
+        assert!(name.starts_with("notes.corrupt-") && name.ends_with(".json"));
+        assert_eq!(
+            fs::read_to_string(&quarantined.moved_to).unwrap(),
+            "{ \"version\": 2, \"notes\": ["
+        );
+
+        // The next load finds nothing, which starts an empty set of notes
+        // rather than failing again.
+        let error = load_notes_from(&path).unwrap_err();
+        assert!(is_not_found(error.as_ref()));
+    }
+}
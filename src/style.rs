@@ -0,0 +1,189 @@
+//! Custom widget styles.
+
+use iced::widget::{button, container};
+use iced::{Color, Theme};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How a note's color shows on its sidebar entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SidebarStyle {
+    /// The whole entry is filled with the color, via `NoteButtonStyle`.
+    #[default]
+    FullBackground,
+    /// A thin `AccentStripe` on a `NeutralNoteButtonStyle` entry.
+    AccentStripe,
+}
+
+impl SidebarStyle {
+    pub const ALL: [SidebarStyle; 2] = [SidebarStyle::FullBackground, SidebarStyle::AccentStripe];
+}
+
+impl fmt::Display for SidebarStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SidebarStyle::FullBackground => "Full background",
+            SidebarStyle::AccentStripe => "Accent stripe",
+        })
+    }
+}
+
+pub fn to_color([r, g, b]: [f32; 3]) -> Color {
+    Color::from_rgb(r, g, b)
+}
+
+/// Black or white, whichever contrasts more with `bg` by the WCAG
+/// relative-luminance formula.
+pub fn readable_text_color(bg: Color) -> Color {
+    let linear = |channel: f32| {
+        if channel <= 0.04045 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let luminance = 0.2126 * linear(bg.r) + 0.7152 * linear(bg.g) + 0.0722 * linear(bg.b);
+    // Where (L + 0.05) / 0.05 equals 1.05 / (L + 0.05), black and white
+    // contrast equally.
+    if luminance > 0.179 {
+        Color::BLACK
+    } else {
+        Color::WHITE
+    }
+}
+
+pub struct NoteButtonStyle(pub [f32; 3]);
+
+impl NoteButtonStyle {
+    fn appearance(&self, background: Color) -> button::Appearance {
+        button::Appearance {
+            background: Some(iced::Background::Color(background)),
+            // Custom colors can be anything, so the label can't assume the
+            // light pastels of the presets.
+            text_color: readable_text_color(background),
+            border: iced::Border::with_radius(5.0),
+            ..Default::default()
+        }
+    }
+}
+
+impl button::StyleSheet for NoteButtonStyle {
+    type Style = Theme;
+
+    fn active(&self, _style: &Self::Style) -> button::Appearance {
+        self.appearance(to_color(self.0))
+    }
+
+    fn hovered(&self, _style: &Self::Style) -> button::Appearance {
+        let [r, g, b] = self.0.map(|channel| channel * 0.9);
+        self.appearance(Color::from_rgb(r, g, b))
+    }
+}
+
+/// A sidebar entry that leaves showing the note's color to an
+/// `AccentStripe`, following the theme instead.
+pub struct NeutralNoteButtonStyle;
+
+impl NeutralNoteButtonStyle {
+    fn appearance(background: iced::theme::palette::Pair) -> button::Appearance {
+        button::Appearance {
+            background: Some(iced::Background::Color(background.color)),
+            text_color: background.text,
+            border: iced::Border::with_radius(5.0),
+            ..Default::default()
+        }
+    }
+}
+
+impl button::StyleSheet for NeutralNoteButtonStyle {
+    type Style = Theme;
+
+    fn active(&self, style: &Self::Style) -> button::Appearance {
+        Self::appearance(style.extended_palette().background.weak)
+    }
+
+    fn hovered(&self, style: &Self::Style) -> button::Appearance {
+        Self::appearance(style.extended_palette().background.strong)
+    }
+}
+
+/// The strip of a note's color along the left edge of its sidebar entry.
+pub struct AccentStripe(pub [f32; 3]);
+
+impl container::StyleSheet for AccentStripe {
+    type Style = Theme;
+
+    fn appearance(&self, _style: &Self::Style) -> container::Appearance {
+        container::Appearance {
+            background: Some(iced::Background::Color(to_color(self.0))),
+            ..Default::default()
+        }
+    }
+}
+
+/// Outlines the window while files are dragged over it.
+pub struct DropZone;
+
+impl container::StyleSheet for DropZone {
+    type Style = Theme;
+
+    fn appearance(&self, style: &Self::Style) -> container::Appearance {
+        let primary = style.extended_palette().primary.base.color;
+        container::Appearance {
+            background: Some(iced::Background::Color(Color { a: 0.08, ..primary })),
+            border: iced::Border {
+                color: primary,
+                width: 3.0,
+                radius: 5.0.into(),
+            },
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::NoteColor;
+
+    #[test]
+    fn labels_contrast_with_black_and_white() {
+        assert_eq!(readable_text_color(Color::WHITE), Color::BLACK);
+        assert_eq!(readable_text_color(Color::BLACK), Color::WHITE);
+    }
+
+    #[test]
+    fn primaries_are_weighed_by_luminance() {
+        assert_eq!(
+            readable_text_color(Color::from_rgb(0.0, 1.0, 0.0)),
+            Color::BLACK
+        );
+        assert_eq!(
+            readable_text_color(Color::from_rgb(1.0, 0.0, 0.0)),
+            Color::BLACK
+        );
+        assert_eq!(
+            readable_text_color(Color::from_rgb(0.0, 0.0, 1.0)),
+            Color::WHITE
+        );
+    }
+
+    #[test]
+    fn grays_switch_either_side_of_the_threshold() {
+        assert_eq!(
+            readable_text_color(Color::from_rgb(0.45, 0.45, 0.45)),
+            Color::WHITE
+        );
+        assert_eq!(
+            readable_text_color(Color::from_rgb(0.5, 0.5, 0.5)),
+            Color::BLACK
+        );
+    }
+
+    #[test]
+    fn preset_pastels_keep_dark_labels() {
+        for color in NoteColor::ALL {
+            assert_eq!(readable_text_color(to_color(color.to_rgb())), Color::BLACK);
+        }
+    }
+}
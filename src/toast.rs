@@ -0,0 +1,66 @@
+//! Short-lived notifications stacked in the bottom-right corner.
+
+use iced::widget::{button, column, container, horizontal_space, row, text};
+use iced::{Alignment, Color, Element, Length};
+use std::time::{Duration, Instant};
+
+use crate::message::Message;
+
+/// How long a toast stays on screen before it is dismissed automatically.
+pub const TOAST_DURATION: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Info,
+    Success,
+}
+
+impl Severity {
+    fn color(self) -> Color {
+        match self {
+            Severity::Error => Color::from_rgb(0.8, 0.0, 0.0),
+            Severity::Info => Color::from_rgb(0.2, 0.4, 0.8),
+            Severity::Success => Color::from_rgb(0.1, 0.6, 0.2),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub text: String,
+    pub severity: Severity,
+    pub spawned: Instant,
+    /// A button label and the message it sends, e.g. to undo what the
+    /// toast reports.
+    pub action: Option<(&'static str, Message)>,
+}
+
+/// Lays out `toasts` oldest first, right-aligned, each with a close button
+/// after its action, if it has one.
+pub fn view(toasts: &[Toast]) -> Element<'_, Message> {
+    let stack = toasts.iter().enumerate().fold(
+        column![].spacing(5).align_items(Alignment::End),
+        |stack, (index, toast)| {
+            let mut content = row![text(&toast.text).style(toast.severity.color())]
+                .spacing(10)
+                .align_items(Alignment::Center);
+            if let Some((label, message)) = &toast.action {
+                content = content.push(button(text(*label).size(12)).on_press(message.clone()));
+            }
+            stack.push(
+                container(
+                    content.push(
+                        button(text("✕").size(12))
+                            .on_press(Message::Dismiss(index))
+                            .style(iced::theme::Button::Text),
+                    ),
+                )
+                .padding(10)
+                .max_width(480)
+                .style(iced::theme::Container::Box),
+            )
+        },
+    );
+    row![horizontal_space(), stack].width(Length::Fill).into()
+}
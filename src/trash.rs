@@ -0,0 +1,82 @@
+//! Deleted notes, kept in the notes file until the trash is emptied.
+
+use iced::widget::{button, column, row, text, Column};
+use iced::Length;
+use std::collections::HashMap;
+
+use crate::message::Message;
+use crate::note::Note;
+
+/// Splits notes read from a file into the live ones and the trash.
+pub fn split(notes: HashMap<String, Note>) -> (HashMap<String, Note>, HashMap<String, Note>) {
+    notes
+        .into_iter()
+        .partition(|(_, note)| note.trashed_at.is_none())
+}
+
+/// The trashed notes, most recently deleted first and by id among equals,
+/// so rows don't trade places between frames.
+fn sorted(trash: &HashMap<String, Note>) -> Vec<&Note> {
+    let mut notes: Vec<&Note> = trash.values().collect();
+    notes.sort_by(|a, b| {
+        b.trashed_at
+            .cmp(&a.trashed_at)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    notes
+}
+
+/// A row per trashed note with a way to restore it, then a button to empty
+/// the trash.
+pub fn view(trash: &HashMap<String, Note>, font_size: u16) -> Column<'_, Message> {
+    sorted(trash)
+        .into_iter()
+        .fold(column![].spacing(5), |column, note| {
+            column.push(
+                row![
+                    text(&note.title).size(font_size).width(Length::Fill),
+                    button("Restore")
+                        .on_press(Message::RestoreNote(note.id.clone()))
+                        .padding(10),
+                ]
+                .spacing(5),
+            )
+        })
+        .push(button("Empty Trash").on_press(Message::EmptyTrash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn trashed(id: &str, day: Option<u32>) -> (String, Note) {
+        let mut note = Note::new(id, String::new());
+        note.id = id.to_string();
+        note.trashed_at = day.map(|day| Utc.with_ymd_and_hms(2024, 5, day, 0, 0, 0).unwrap());
+        (note.id.clone(), note)
+    }
+
+    #[test]
+    fn split_separates_trashed_notes() {
+        let (live, trash) = split(HashMap::from([
+            trashed("kept", None),
+            trashed("gone", Some(1)),
+        ]));
+        assert!(live.contains_key("kept"));
+        assert!(trash.contains_key("gone"));
+        assert_eq!((live.len(), trash.len()), (1, 1));
+    }
+
+    #[test]
+    fn newest_deletions_come_first_in_a_stable_order() {
+        let trash = HashMap::from([
+            trashed("b", Some(2)),
+            trashed("old", Some(1)),
+            trashed("a", Some(2)),
+            trashed("new", Some(9)),
+        ]);
+        let ids: Vec<&str> = sorted(&trash).iter().map(|note| note.id.as_str()).collect();
+        assert_eq!(ids, ["new", "a", "b", "old"]);
+    }
+}
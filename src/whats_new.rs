@@ -0,0 +1,52 @@
+//! The "What's new" panel shown once after the app is updated.
+
+use iced::widget::{button, column, container, horizontal_space, row, text};
+use iced::{Alignment, Element, Length};
+
+use crate::message::Message;
+
+/// The running version, compared against the last one the panel was
+/// dismissed for.
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Recent features, newest first.
+const FEATURES: &[&str] = &[
+    "Notes changed on disk by another app are no longer overwritten silently",
+    "Sorting by date groups notes under Today, Yesterday and older headers",
+    "Notes can be shown in a monospace font, for code or ASCII art",
+    "Hovering a pinned note peeks at its first lines",
+    "Copy a note to the clipboard as text or Markdown",
+    "Save just the current note with ⌘S",
+];
+
+/// Whether `current` is a later version than `seen`, comparing the
+/// dot-separated numbers in turn. A missing `seen` means the panel was
+/// never dismissed, so everything is new.
+pub fn is_newer(current: &str, seen: Option<&str>) -> bool {
+    let parse = |version: &str| -> Vec<u64> {
+        version
+            .split('.')
+            .map(|part| part.trim().parse().unwrap_or_default())
+            .collect()
+    };
+    seen.is_none_or(|seen| parse(current) > parse(seen))
+}
+
+pub fn view<'a>() -> Element<'a, Message> {
+    let header = row![
+        text(format!("What's new in {}", CURRENT_VERSION)).size(16),
+        horizontal_space(),
+        button(text("Got it").size(12)).on_press(Message::DismissWhatsNew),
+    ]
+    .align_items(Alignment::Center);
+    let list = FEATURES
+        .iter()
+        .fold(column![header].spacing(5), |list, feature| {
+            list.push(text(format!("• {}", feature)).size(12))
+        });
+    container(list)
+        .padding(10)
+        .width(Length::Fill)
+        .style(iced::theme::Container::Box)
+        .into()
+}